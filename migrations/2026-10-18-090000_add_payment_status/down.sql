@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX payments_paid_ad_order_id_idx;
+
+ALTER TABLE payments DROP COLUMN status;
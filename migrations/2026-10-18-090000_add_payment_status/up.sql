@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE payments ADD COLUMN status TEXT NOT NULL DEFAULT 'Paid';
+
+CREATE UNIQUE INDEX payments_paid_ad_order_id_idx ON payments (ad_order_id) WHERE status = 'Paid';
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX payments_provider_reference_idx;
+DROP INDEX payments_active_ad_order_id_idx;
+
+UPDATE payments SET status = 'Paid' WHERE status IN ('Authorized', 'Captured');
+UPDATE payments SET status = 'Refunded' WHERE status = 'Voided';
+ALTER TABLE payments ALTER COLUMN status SET DEFAULT 'Paid';
+
+CREATE UNIQUE INDEX payments_paid_ad_order_id_idx ON payments (ad_order_id) WHERE status = 'Paid';
+
+ALTER TABLE payments DROP COLUMN provider_reference;
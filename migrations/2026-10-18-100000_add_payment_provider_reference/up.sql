@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE payments ADD COLUMN provider_reference TEXT NOT NULL DEFAULT '';
+
+DROP INDEX payments_paid_ad_order_id_idx;
+
+UPDATE payments SET status = 'Captured' WHERE status = 'Paid';
+ALTER TABLE payments ALTER COLUMN status SET DEFAULT 'Authorized';
+
+CREATE UNIQUE INDEX payments_active_ad_order_id_idx ON payments (ad_order_id)
+    WHERE status IN ('Authorized', 'Captured');
+CREATE INDEX payments_provider_reference_idx ON payments (provider_reference);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE ad_orders DROP CONSTRAINT ad_orders_no_overlap;
+ALTER TABLE ad_orders DROP CONSTRAINT ad_orders_time_range_check;
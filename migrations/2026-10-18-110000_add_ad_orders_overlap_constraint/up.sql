@@ -0,0 +1,69 @@
+-- Your SQL goes here
+CREATE EXTENSION IF NOT EXISTS btree_gist;
+
+-- Orders with an empty or inverted window cannot be repaired automatically. The
+-- migration stops and lists them so they can be fixed by hand first; the same
+-- rows are found with:
+--
+--   SELECT ad_order_id, screen_id, start_time, end_time
+--   FROM ad_orders WHERE end_time <= start_time;
+DO $$
+DECLARE
+    invalid_orders TEXT;
+BEGIN
+    SELECT string_agg(ad_order_id::TEXT, ', ') INTO invalid_orders
+    FROM ad_orders
+    WHERE end_time <= start_time;
+
+    IF invalid_orders IS NOT NULL THEN
+        RAISE EXCEPTION 'Ad orders end before they start: %', invalid_orders;
+    END IF;
+END $$;
+
+ALTER TABLE ad_orders
+    ADD CONSTRAINT ad_orders_time_range_check CHECK (end_time > start_time);
+
+-- Approved orders that already overlap keep the one that starts first on each
+-- screen and reject the later ones, logging each. They can be listed before
+-- migrating with:
+--
+--   SELECT later.ad_order_id, earlier.ad_order_id AS overlaps, later.screen_id
+--   FROM ad_orders later
+--   JOIN ad_orders earlier
+--     ON earlier.screen_id = later.screen_id
+--    AND (earlier.start_time, earlier.ad_order_id) < (later.start_time, later.ad_order_id)
+--    AND earlier.start_time < later.end_time
+--    AND later.start_time < earlier.end_time
+--   WHERE NOT later.is_rejected AND NOT earlier.is_rejected;
+DO $$
+DECLARE
+    ad_order RECORD;
+BEGIN
+    FOR ad_order IN
+        SELECT ad_order_id, screen_id, start_time, end_time
+        FROM ad_orders
+        WHERE NOT is_rejected
+        ORDER BY screen_id, start_time, ad_order_id
+    LOOP
+        IF EXISTS (
+            SELECT 1
+            FROM ad_orders earlier
+            WHERE earlier.screen_id = ad_order.screen_id
+              AND NOT earlier.is_rejected
+              AND (earlier.start_time, earlier.ad_order_id) < (ad_order.start_time, ad_order.ad_order_id)
+              AND earlier.start_time < ad_order.end_time
+              AND ad_order.start_time < earlier.end_time
+        ) THEN
+            UPDATE ad_orders SET is_rejected = true WHERE ad_order_id = ad_order.ad_order_id;
+            RAISE WARNING 'Rejected ad order % overlapping an earlier order on screen %',
+                ad_order.ad_order_id, ad_order.screen_id;
+        END IF;
+    END LOOP;
+END $$;
+
+-- Approved orders may not share a screen for overlapping windows. Ranges are
+-- half-open, so back-to-back bookings are allowed.
+ALTER TABLE ad_orders
+    ADD CONSTRAINT ad_orders_no_overlap
+    EXCLUDE USING gist (screen_id WITH =, tstzrange(start_time, end_time) WITH &&)
+    WHERE (NOT is_rejected);
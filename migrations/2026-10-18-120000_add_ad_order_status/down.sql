@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE ad_orders DROP CONSTRAINT ad_orders_no_overlap;
+
+ALTER TABLE ad_orders ADD COLUMN is_rejected BOOL NOT NULL DEFAULT true;
+UPDATE ad_orders SET is_rejected = status NOT IN ('Approved', 'Running', 'Completed');
+ALTER TABLE ad_orders ALTER COLUMN is_rejected DROP DEFAULT;
+
+ALTER TABLE ad_orders DROP CONSTRAINT ad_orders_status_check;
+ALTER TABLE ad_orders DROP COLUMN status;
+
+ALTER TABLE ad_orders
+    ADD CONSTRAINT ad_orders_no_overlap
+    EXCLUDE USING gist (screen_id WITH =, tstzrange(start_time, end_time) WITH &&)
+    WHERE (NOT is_rejected);
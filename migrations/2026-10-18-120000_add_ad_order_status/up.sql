@@ -0,0 +1,23 @@
+-- Your SQL goes here
+ALTER TABLE ad_orders ADD COLUMN status TEXT NOT NULL DEFAULT 'Pending';
+
+-- Approving an order records an income that stays behind when the order is later
+-- rejected, which is the only way to tell rejected orders from pending ones.
+UPDATE ad_orders
+SET status = CASE
+    WHEN NOT is_rejected THEN 'Approved'
+    WHEN ad_order_id IN (SELECT ad_order_id FROM incomes) THEN 'Rejected'
+    ELSE 'Pending'
+END;
+
+ALTER TABLE ad_orders
+    ADD CONSTRAINT ad_orders_status_check
+    CHECK (status IN ('Pending', 'Approved', 'Rejected', 'Cancelled', 'Running', 'Completed'));
+
+ALTER TABLE ad_orders DROP CONSTRAINT ad_orders_no_overlap;
+ALTER TABLE ad_orders DROP COLUMN is_rejected;
+
+ALTER TABLE ad_orders
+    ADD CONSTRAINT ad_orders_no_overlap
+    EXCLUDE USING gist (screen_id WITH =, tstzrange(start_time, end_time) WITH &&)
+    WHERE (status IN ('Approved', 'Running'));
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE audit_logs (
+    audit_log_id UUID PRIMARY KEY NOT NULL,
+    admin_id UUID NOT NULL,
+    action TEXT NOT NULL,
+    target_id UUID NOT NULL,
+    owner_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    FOREIGN KEY(admin_id) REFERENCES admin (admin_id)
+);
+
+CREATE INDEX audit_logs_created_at_idx ON audit_logs (created_at);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE screens DROP CONSTRAINT screens_status_check;
+ALTER TABLE screens DROP COLUMN status;
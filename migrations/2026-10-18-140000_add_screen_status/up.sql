@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE screens ADD COLUMN status TEXT NOT NULL DEFAULT 'Active';
+
+ALTER TABLE screens
+    ADD CONSTRAINT screens_status_check
+    CHECK (status IN ('Pending', 'Active', 'Rejected', 'Archived'));
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE screens
+    ALTER COLUMN characteristics TYPE TEXT
+    USING CASE
+        WHEN characteristics - 'description' = '{}'::JSONB THEN characteristics ->> 'description'
+        ELSE characteristics::TEXT
+    END;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- Free-text characteristics are kept as the description of the structured value.
+ALTER TABLE screens
+    ALTER COLUMN characteristics TYPE JSONB
+    USING jsonb_build_object('description', characteristics);
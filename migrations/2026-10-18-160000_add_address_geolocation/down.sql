@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX addresses_coordinates_idx;
+
+ALTER TABLE addresses
+    DROP CONSTRAINT addresses_coordinates_check,
+    DROP COLUMN region,
+    DROP COLUMN city,
+    DROP COLUMN longitude,
+    DROP COLUMN latitude;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+ALTER TABLE addresses
+    ADD COLUMN latitude DOUBLE PRECISION,
+    ADD COLUMN longitude DOUBLE PRECISION,
+    ADD COLUMN city TEXT,
+    ADD COLUMN region TEXT;
+
+ALTER TABLE addresses
+    ADD CONSTRAINT addresses_coordinates_check CHECK (
+        (latitude IS NULL AND longitude IS NULL)
+        OR (latitude BETWEEN -90 AND 90 AND longitude BETWEEN -180 AND 180)
+    );
+
+CREATE INDEX addresses_coordinates_idx ON addresses (latitude, longitude);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE ad_orders DROP COLUMN campaign_id;
+
+DROP TABLE campaigns;
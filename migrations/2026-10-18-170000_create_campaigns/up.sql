@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TABLE campaigns (
+    campaign_id UUID PRIMARY KEY NOT NULL,
+    campaign_name TEXT NOT NULL,
+    user_id UUID NOT NULL,
+    ad_id UUID NOT NULL,
+    budget DOUBLE PRECISION NOT NULL,
+    total_price DOUBLE PRECISION NOT NULL,
+    provider_reference TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    FOREIGN KEY(user_id) REFERENCES users (user_id),
+    FOREIGN KEY(ad_id) REFERENCES ads (ad_id)
+);
+
+CREATE INDEX campaigns_user_id_idx ON campaigns (user_id);
+
+ALTER TABLE ad_orders ADD COLUMN campaign_id UUID REFERENCES campaigns (campaign_id);
+
+CREATE INDEX ad_orders_campaign_id_idx ON ad_orders (campaign_id);
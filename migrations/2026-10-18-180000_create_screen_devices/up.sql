@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE screen_devices (
+    screen_id UUID PRIMARY KEY NOT NULL,
+    api_key_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    FOREIGN KEY(screen_id) REFERENCES screens (screen_id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX screen_devices_api_key_hash_idx ON screen_devices (api_key_hash);
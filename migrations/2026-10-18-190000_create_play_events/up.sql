@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE play_events (
+    play_event_id UUID PRIMARY KEY NOT NULL,
+    ad_order_id UUID NOT NULL,
+    screen_id UUID NOT NULL,
+    played_at TIMESTAMPTZ NOT NULL,
+    duration_ms INTEGER NOT NULL CHECK (duration_ms > 0),
+    received_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    FOREIGN KEY(ad_order_id) REFERENCES ad_orders (ad_order_id),
+    FOREIGN KEY(screen_id) REFERENCES screens (screen_id) ON DELETE CASCADE
+);
+
+CREATE INDEX play_events_ad_order_id_idx ON play_events (ad_order_id);
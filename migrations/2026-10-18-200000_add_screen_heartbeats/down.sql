@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE ad_orders DROP COLUMN offline_flagged_at;
+
+ALTER TABLE screen_devices
+    DROP COLUMN free_storage_bytes,
+    DROP COLUMN software_version,
+    DROP COLUMN last_seen_at;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE screen_devices
+    ADD COLUMN last_seen_at TIMESTAMPTZ,
+    ADD COLUMN software_version TEXT,
+    ADD COLUMN free_storage_bytes BIGINT CHECK (free_storage_bytes >= 0);
+
+ALTER TABLE ad_orders ADD COLUMN offline_flagged_at TIMESTAMPTZ;
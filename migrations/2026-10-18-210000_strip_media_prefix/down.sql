@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+UPDATE users SET img_url = 'media/' || img_url WHERE img_url <> '' AND img_url NOT LIKE '%/%';
+UPDATE businesses SET img_url = 'media/' || img_url WHERE img_url <> '' AND img_url NOT LIKE '%/%';
+UPDATE ads SET img_url = 'media/' || img_url WHERE img_url <> '' AND img_url NOT LIKE '%/%';
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+-- Media is referenced by id and served from /images/{id}, so the storage directory is dropped.
+UPDATE users SET img_url = substr(img_url, length('media/') + 1) WHERE img_url LIKE 'media/%';
+UPDATE businesses SET img_url = substr(img_url, length('media/') + 1) WHERE img_url LIKE 'media/%';
+UPDATE ads SET img_url = substr(img_url, length('media/') + 1) WHERE img_url LIKE 'media/%';
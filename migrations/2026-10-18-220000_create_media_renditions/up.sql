@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE media_renditions (
+    media_id TEXT NOT NULL,
+    rendition TEXT NOT NULL,
+    rendition_media_id TEXT NOT NULL UNIQUE,
+    width INTEGER NOT NULL CHECK (width > 0),
+    height INTEGER NOT NULL CHECK (height > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+    PRIMARY KEY (media_id, rendition)
+);
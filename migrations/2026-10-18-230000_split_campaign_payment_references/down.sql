@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX payments_provider_reference_idx;
+CREATE INDEX payments_provider_reference_idx ON payments (provider_reference);
+
+UPDATE payments
+SET provider_reference = campaigns.provider_reference
+FROM ad_orders
+JOIN campaigns ON campaigns.campaign_id = ad_orders.campaign_id
+WHERE ad_orders.ad_order_id = payments.ad_order_id
+  AND payments.provider_reference = campaigns.provider_reference || '/' || payments.ad_order_id;
@@ -1,18 +1,62 @@
+use crate::actors::audit_log::record_admin_override;
 use crate::actors::db::{get_pooled_connection, DbActor};
-use crate::errors::AppError;
-use crate::models::ad::{Ad, AdStatus};
+use crate::actors::media::{find_renditions, renditions_for};
+use crate::errors::{AppError, AppErrorType};
+use crate::models::ad::{Ad, AdInfo, AdStatus};
+use crate::models::audit_log::AuditAction;
+use crate::models::category::AdCategory;
+use crate::schema::ad_categories::dsl::ad_categories;
 use crate::schema::ads::dsl::ads;
 use crate::schema::ads::{ad_id, ad_name, img_url, user_id};
 use actix::{Handler, Message};
 use diesel::expression_methods::ExpressionMethods;
-use diesel::{Connection, QueryDsl, RunQueryDsl};
+use diesel::{Connection, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl};
 use slog::{o, Logger};
 use uuid::Uuid;
-use crate::models::category::AdCategory;
-use crate::schema::ad_categories::dsl::ad_categories;
+
+/// Returns the owner of the ad after making sure the caller may use it. Admins may use
+/// any ad, but only when they ask for it explicitly.
+pub fn check_ad_owner(
+    conn: &mut PgConnection,
+    id: Uuid,
+    caller_id: Uuid,
+    admin_override: bool,
+) -> Result<Uuid, AppError> {
+    let wrapped_owner_id: Option<Uuid> = ads.find(id).select(user_id).first(conn).optional()?;
+
+    match wrapped_owner_id {
+        Some(owner_id) if owner_id == caller_id || admin_override => Ok(owner_id),
+        Some(_) => Err(AppError::new(
+            Some("Ad belongs to another user".to_string()),
+            None,
+            AppErrorType::ForbiddenError,
+        )),
+        None => Err(AppError::new(
+            Some("Ad not found".to_string()),
+            None,
+            AppErrorType::NotFoundError,
+        )),
+    }
+}
+
+/// Attaches the renditions of their images to ads.
+fn with_renditions(conn: &mut PgConnection, found_ads: Vec<Ad>) -> QueryResult<Vec<AdInfo>> {
+    let renditions = find_renditions(
+        conn,
+        found_ads.iter().map(|ad| ad.img_url.clone()).collect(),
+    )?;
+
+    Ok(found_ads
+        .into_iter()
+        .map(|ad| AdInfo {
+            img_renditions: renditions_for(&renditions, &ad.img_url),
+            ad,
+        })
+        .collect())
+}
 
 #[derive(Message)]
-#[rtype(result = "Result<Ad, AppError>")]
+#[rtype(result = "Result<AdInfo, AppError>")]
 pub struct CreateAd {
     pub ad_name: String,
     pub img_url: String,
@@ -22,28 +66,30 @@ pub struct CreateAd {
 }
 
 #[derive(Message)]
-#[rtype(result = "Result<Ad, AppError>")]
+#[rtype(result = "Result<AdInfo, AppError>")]
 pub struct UpdateAd {
     pub id: Uuid,
     pub ad_name: String,
     pub img_url: String,
+    /// Caller making the change; only the ad's owner may edit it unless `admin_override` is set.
     pub user_id: Uuid,
+    pub admin_override: bool,
     pub logger: Logger,
 }
 
 #[derive(Message)]
-#[rtype(result = "Result<Vec<Ad>, AppError>")]
+#[rtype(result = "Result<Vec<AdInfo>, AppError>")]
 pub struct GetAllAds;
 
 #[derive(Message)]
-#[rtype(result = "Result<Vec<Ad>, AppError>")]
+#[rtype(result = "Result<Vec<AdInfo>, AppError>")]
 pub struct GetUserAds {
     pub user_id: Uuid,
     pub logger: Logger,
 }
 
 impl Handler<CreateAd> for DbActor {
-    type Result = Result<Ad, AppError>;
+    type Result = Result<AdInfo, AppError>;
 
     fn handle(&mut self, msg: CreateAd, _: &mut Self::Context) -> Self::Result {
         let sub_log = msg.logger.new(o!("handle" => "create_ad"));
@@ -65,13 +111,12 @@ impl Handler<CreateAd> for DbActor {
             let mut ad_cats: Vec<AdCategory> = Vec::new();
             for ad_cat_id in msg.categories_id {
                 let ad_category = AdCategory {
-                    ad_id: new_ad.ad_id.clone(),
+                    ad_id: new_ad.ad_id,
                     category_id: ad_cat_id,
                 };
                 ad_cats.push(ad_category);
             }
 
-
             diesel::insert_into(ad_categories)
                 .values(ad_cats)
                 .get_result::<AdCategory>(conn)?;
@@ -79,48 +124,58 @@ impl Handler<CreateAd> for DbActor {
             Ok(ad)
         })?;
 
-        Ok(result)
+        let mut ad_infos = with_renditions(&mut conn, vec![result])?;
+        Ok(ad_infos.remove(0))
     }
 }
 
 impl Handler<UpdateAd> for DbActor {
-    type Result = Result<Ad, AppError>;
+    type Result = Result<AdInfo, AppError>;
 
     fn handle(&mut self, msg: UpdateAd, _: &mut Self::Context) -> Self::Result {
         let sub_log = msg.logger.new(o!("handle" => "update_ad"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
-        let updated_category = diesel::update(ads)
-            .filter(ad_id.eq(msg.id))
-            .set((
-                ad_name.eq(msg.ad_name),
-                img_url.eq(msg.img_url),
-                user_id.eq(msg.user_id),
-            ))
-            .get_result::<Ad>(&mut conn)?;
+        let owner_id = check_ad_owner(&mut conn, msg.id, msg.user_id, msg.admin_override)?;
+
+        let updated_ad = conn.transaction::<_, AppError, _>(|conn| {
+            let updated_ad = diesel::update(ads)
+                .filter(ad_id.eq(msg.id))
+                .set((ad_name.eq(msg.ad_name), img_url.eq(msg.img_url)))
+                .get_result::<Ad>(conn)?;
+
+            if owner_id != msg.user_id {
+                record_admin_override(conn, msg.user_id, AuditAction::UpdateAd, msg.id, owner_id)?;
+            }
+
+            Ok(updated_ad)
+        })?;
 
-        Ok(updated_category)
+        let mut ad_infos = with_renditions(&mut conn, vec![updated_ad])?;
+        Ok(ad_infos.remove(0))
     }
 }
 
 impl Handler<GetAllAds> for DbActor {
-    type Result = Result<Vec<Ad>, AppError>;
+    type Result = Result<Vec<AdInfo>, AppError>;
 
     fn handle(&mut self, _: GetAllAds, _: &mut Self::Context) -> Self::Result {
         let mut conn = self.0.get()?;
         let result = ads.get_results::<Ad>(&mut conn)?;
-        Ok(result)
+        Ok(with_renditions(&mut conn, result)?)
     }
 }
 
 impl Handler<GetUserAds> for DbActor {
-    type Result = Result<Vec<Ad>, AppError>;
+    type Result = Result<Vec<AdInfo>, AppError>;
 
     fn handle(&mut self, msg: GetUserAds, _: &mut Self::Context) -> Self::Result {
         let sub_log = msg.logger.new(o!("handle" => "get_user_ads"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
-        let result = ads.filter(user_id.eq(msg.user_id)).get_results::<Ad>(&mut conn)?;
-        Ok(result)
+        let result = ads
+            .filter(user_id.eq(msg.user_id))
+            .get_results::<Ad>(&mut conn)?;
+        Ok(with_renditions(&mut conn, result)?)
     }
-}
\ No newline at end of file
+}
@@ -1,45 +1,125 @@
+use crate::actors::ad::check_ad_owner;
+use crate::actors::audit_log::record_admin_override;
 use crate::actors::db::{get_pooled_connection, DbActor};
+use crate::actors::income::reverse_ad_order_income;
+use crate::actors::media::{find_renditions, renditions_for};
+use crate::actors::payment::{
+    capture_payment, find_active_payment, release_payment, PRICE_TOLERANCE,
+};
+use crate::config::BillingUnit;
+use crate::db_utils::{current_pg_timestamp, PG_EPOCH_UNIX_MICROS};
 use crate::errors::{AppError, AppErrorType};
-use crate::models::ad::{Ad, AdStatus};
-use crate::models::ad_order::{AdOrder, AdOrderAllData};
+use crate::models::ad::{Ad, AdInfo, AdStatus};
+use crate::models::ad_order::{
+    AdOrder, AdOrderAllData, AdOrderQuote, AdOrderStatus, RecurrenceFrequency,
+    RecurrenceOccurrence, RecurrencePreview, RecurrenceRule, RecurringAdOrders, UserAdOrderData,
+};
 use crate::models::address::Address;
+use crate::models::audit_log::AuditAction;
 use crate::models::income::Income;
-use crate::models::screen::Screen;
-use crate::models::user::User;
+use crate::models::payment::PaymentStatus;
+use crate::models::screen::{Screen, ScreenStatus};
+use crate::models::user::{User, UserInfo};
+use crate::payment_provider::PaymentProvider;
 use crate::schema::ad_orders::dsl::ad_orders;
 use crate::schema::ad_orders::{
-    ad_id as ad_orders_ad_id_column, ad_order_id as order_id_column,
-    is_rejected as is_rejected_column, screen_id as ad_orders_screen_id_column,
+    ad_id as ad_orders_ad_id_column, ad_order_id as order_id_column, end_time as end_time_column,
+    screen_id as ad_orders_screen_id_column, start_time as start_time_column,
+    status as ad_order_status_column,
 };
 use crate::schema::addresses::address_id as address_id_column;
 use crate::schema::addresses::dsl::addresses;
 use crate::schema::ads::dsl::ads;
 use crate::schema::ads::{ad_id as ad_id_column, user_id as ads_user_id_column};
-use crate::schema::businesses::business_id as business_id_column;
-use crate::schema::businesses::dsl::businesses;
 use crate::schema::incomes::dsl::incomes;
+use crate::schema::payments::dsl::payments;
+use crate::schema::payments::{
+    ad_order_id as payment_order_id_column, status as payment_status_column,
+};
 use crate::schema::screens::dsl::screens;
 use crate::schema::screens::{
     address_id as screen_address_id_column, business_id as screen_business_id_column,
-    screen_id as screen_id_column,
+    price_per_time as price_per_time_column, screen_id as screen_id_column,
+    status as screen_status_column,
 };
 use crate::schema::users::dsl::users;
 use crate::schema::users::user_id as user_id_column;
 use actix::{Handler, Message};
+use chrono::{Datelike, Duration, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
 use diesel::data_types::PgTimestamp;
+use diesel::dsl::{exists, now};
 use diesel::expression_methods::ExpressionMethods;
-use diesel::{Connection, JoinOnDsl, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
-use slog::{o, Logger};
+use diesel::pg::Pg;
+use diesel::{
+    select, Connection, JoinOnDsl, OptionalExtension, PgConnection, QueryDsl, QueryResult,
+    RunQueryDsl, SelectableHelper,
+};
+use slog::{o, warn, Logger};
+use std::str::FromStr;
+use std::sync::Arc;
 use uuid::Uuid;
 
 #[derive(Message)]
-#[rtype(result = "Result<(), AppError>")]
+#[rtype(result = "Result<Uuid, AppError>")]
 pub struct CreateAdOrder {
     pub start_time: i64,
     pub end_time: i64,
-    pub price: f64,
+    pub price: Option<f64>,
+    pub ad_id: Uuid,
+    pub screen_id: Uuid,
+    /// Caller placing the order; only the ad's owner may book with it unless `admin_override`
+    /// is set.
+    pub user_id: Uuid,
+    pub admin_override: bool,
+    pub include_pending: bool,
+    pub billing_unit: BillingUnit,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<RecurrencePreview, AppError>")]
+pub struct PreviewRecurringAdOrder {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub recurrence: RecurrenceRule,
+    pub screen_id: Uuid,
+    pub include_pending: bool,
+    pub billing_unit: BillingUnit,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<RecurringAdOrders, AppError>")]
+pub struct CreateRecurringAdOrder {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub recurrence: RecurrenceRule,
+    pub price: Option<f64>,
     pub ad_id: Uuid,
     pub screen_id: Uuid,
+    pub user_id: Uuid,
+    pub admin_override: bool,
+    pub skip_unavailable: bool,
+    pub include_pending: bool,
+    pub billing_unit: BillingUnit,
+    pub logger: Logger,
+}
+
+const MICROS_PER_DAY: i64 = 86_400_000_000;
+/// Orders start and end within about a century of 2000-01-01, which keeps the arithmetic on
+/// their windows far from overflowing.
+const MAX_ORDER_TIME: i64 = 36_525 * MICROS_PER_DAY;
+/// Most occurrences a recurrence rule can expand into.
+const MAX_RECURRENCE_OCCURRENCES: usize = 366;
+
+#[derive(Message)]
+#[rtype(result = "Result<AdOrderQuote, AppError>")]
+pub struct GetAdOrderQuote {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub screen_id: Uuid,
+    pub billing_unit: BillingUnit,
     pub logger: Logger,
 }
 
@@ -50,10 +130,24 @@ pub struct GetBusinessAdOrders {
     pub logger: Logger,
 }
 
+#[derive(Message)]
+#[rtype(result = "Result<Vec<UserAdOrderData>, AppError>")]
+pub struct GetUserAdOrders {
+    pub user_id: Uuid,
+    pub statuses: Option<Vec<AdOrderStatus>>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub logger: Logger,
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<(), AppError>")]
 pub struct RejectAdOrder {
     pub ad_order_id: Uuid,
+    pub business_id: Uuid,
+    /// Lets an admin act on an order of any business; such actions are audited.
+    pub admin_override: bool,
+    pub payment_provider: Arc<dyn PaymentProvider>,
     pub logger: Logger,
 }
 
@@ -61,48 +155,679 @@ pub struct RejectAdOrder {
 #[rtype(result = "Result<(), AppError>")]
 pub struct ApproveAdOrder {
     pub ad_order_id: Uuid,
+    pub business_id: Uuid,
+    /// Lets an admin act on an order of any business; such actions are audited.
+    pub admin_override: bool,
+    pub payment_provider: Arc<dyn PaymentProvider>,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), AppError>")]
+pub struct CancelAdOrder {
+    pub ad_order_id: Uuid,
+    pub user_id: Uuid,
+    pub cancellation_window_hours: i64,
+    pub payment_provider: Arc<dyn PaymentProvider>,
+    pub logger: Logger,
+}
+
+/// Starts approved orders whose window has begun and completes running ones that have ended.
+#[derive(Message)]
+#[rtype(result = "Result<usize, AppError>")]
+pub struct SyncAdOrderStatuses {
     pub logger: Logger,
 }
 
+/// Orders that hold part of the window on any screen. Approved and running orders always hold
+/// their time; pending ones only when `include_pending` is set.
+fn window_orders(
+    start_time: PgTimestamp,
+    end_time: PgTimestamp,
+    include_pending: bool,
+) -> crate::schema::ad_orders::BoxedQuery<'static, Pg> {
+    let mut statuses = AdOrderStatus::holding();
+    if include_pending {
+        statuses.push(AdOrderStatus::Pending.to_string());
+    }
+
+    ad_orders
+        .filter(start_time_column.lt(end_time))
+        .filter(end_time_column.gt(start_time))
+        .filter(ad_order_status_column.eq_any(statuses))
+        .into_boxed()
+}
+
+/// Orders on the screen that hold part of the window.
+fn booked_orders(
+    screen_id: Uuid,
+    start_time: PgTimestamp,
+    end_time: PgTimestamp,
+    include_pending: bool,
+) -> crate::schema::ad_orders::BoxedQuery<'static, Pg> {
+    window_orders(start_time, end_time, include_pending)
+        .filter(ad_orders_screen_id_column.eq(screen_id))
+}
+
+/// Returns every screen that is already booked for part of the window.
+pub fn find_booked_screen_ids(
+    conn: &mut PgConnection,
+    start_time: PgTimestamp,
+    end_time: PgTimestamp,
+    include_pending: bool,
+) -> QueryResult<Vec<Uuid>> {
+    window_orders(start_time, end_time, include_pending)
+        .select(ad_orders_screen_id_column)
+        .distinct()
+        .load(conn)
+}
+
+/// Returns an order that already holds the screen for part of the window.
+pub fn find_conflicting_order(
+    conn: &mut PgConnection,
+    screen_id: Uuid,
+    start_time: PgTimestamp,
+    end_time: PgTimestamp,
+    include_pending: bool,
+) -> QueryResult<Option<Uuid>> {
+    booked_orders(screen_id, start_time, end_time, include_pending)
+        .select(order_id_column)
+        .first::<Uuid>(conn)
+        .optional()
+}
+
+/// Returns true when the screen has orders that are still waiting for approval or have yet to
+/// finish playing.
+pub fn screen_has_upcoming_orders(conn: &mut PgConnection, screen_id: Uuid) -> QueryResult<bool> {
+    let mut statuses = AdOrderStatus::holding();
+    statuses.push(AdOrderStatus::Pending.to_string());
+
+    select(exists(
+        ad_orders
+            .filter(ad_orders_screen_id_column.eq(screen_id))
+            .filter(ad_order_status_column.eq_any(statuses))
+            .filter(end_time_column.gt(now)),
+    ))
+    .get_result(conn)
+}
+
+pub fn screen_has_orders(conn: &mut PgConnection, screen_id: Uuid) -> QueryResult<bool> {
+    select(exists(
+        ad_orders.filter(ad_orders_screen_id_column.eq(screen_id)),
+    ))
+    .get_result(conn)
+}
+
+/// Returns the `(start_time, end_time)` of every order holding part of the window,
+/// ordered by start time.
+pub fn find_booked_times(
+    conn: &mut PgConnection,
+    screen_id: Uuid,
+    start_time: PgTimestamp,
+    end_time: PgTimestamp,
+    include_pending: bool,
+) -> QueryResult<Vec<(PgTimestamp, PgTimestamp)>> {
+    booked_orders(screen_id, start_time, end_time, include_pending)
+        .select((start_time_column, end_time_column))
+        .order(start_time_column.asc())
+        .load(conn)
+}
+
+/// Only verified ads can be booked.
+pub fn check_ad_bookable(conn: &mut PgConnection, ad_id: Uuid) -> Result<(), AppError> {
+    let ad: Ad = ads.find(ad_id).first::<Ad>(conn)?;
+
+    if ad.status == AdStatus::Unverified.to_string() {
+        let message = Some("Ad is unverified".to_string());
+        return Err(AppError::new(
+            message,
+            None,
+            AppErrorType::UnverifiedAdError,
+        ));
+    } else if ad.status == AdStatus::Rejected.to_string() {
+        let message = Some("Ad is rejected".to_string());
+        return Err(AppError::new(message, None, AppErrorType::RejectedAdError));
+    }
+
+    Ok(())
+}
+
+fn check_time_range(start_time: i64, end_time: i64) -> Result<(), AppError> {
+    let allowed = -MAX_ORDER_TIME..=MAX_ORDER_TIME;
+    if !allowed.contains(&start_time) || !allowed.contains(&end_time) {
+        return Err(AppError::new(
+            Some("Ad order must fall between the years 1900 and 2100".to_string()),
+            None,
+            AppErrorType::ValidationError,
+        ));
+    }
+    if end_time <= start_time {
+        return Err(AppError::new(
+            Some("Ad order must end after it starts".to_string()),
+            None,
+            AppErrorType::ValidationError,
+        ));
+    }
+    Ok(())
+}
+
+/// Prices a booking from the screen's `price_per_time`, charging every started billing unit.
+pub fn quote_ad_order(
+    conn: &mut PgConnection,
+    screen_id: Uuid,
+    start_time: i64,
+    end_time: i64,
+    billing_unit: BillingUnit,
+) -> Result<AdOrderQuote, AppError> {
+    check_time_range(start_time, end_time)?;
+
+    let wrapped_screen: Option<(f64, String)> = screens
+        .find(screen_id)
+        .select((price_per_time_column, screen_status_column))
+        .first(conn)
+        .optional()?;
+
+    let price_per_time = match wrapped_screen {
+        Some((price_per_time, status)) if status == ScreenStatus::Active.to_string() => {
+            price_per_time
+        }
+        Some(_) => {
+            return Err(AppError::new(
+                Some("Screen is not available for booking".to_string()),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+        None => {
+            return Err(AppError::new(
+                Some("Screen not found".to_string()),
+                None,
+                AppErrorType::NotFoundError,
+            ));
+        }
+    };
+
+    let billed_units = billing_unit.billed_units(start_time, end_time)?;
+
+    Ok(AdOrderQuote {
+        screen_id,
+        start_time,
+        end_time,
+        billed_units,
+        price: price_per_time * billed_units as f64,
+    })
+}
+
+fn recurrence_error(message: &str) -> AppError {
+    AppError::new(
+        Some(message.to_string()),
+        None,
+        AppErrorType::ValidationError,
+    )
+}
+
+/// Wall-clock time in `timezone` of a timestamp.
+fn local_time_of(timezone: Tz, time: i64) -> Result<NaiveDateTime, AppError> {
+    NaiveDateTime::from_timestamp_micros(time + PG_EPOCH_UNIX_MICROS)
+        .map(|utc| timezone.from_utc_datetime(&utc).naive_local())
+        .ok_or_else(|| recurrence_error("Order time is out of range"))
+}
+
+/// Timestamp of a wall-clock time in `timezone`. A time that happens twice when the clocks go
+/// back is taken the first time, and a time skipped when they go forward is read with the offset
+/// from before the change, as RFC 5545 does.
+fn pg_time_of(timezone: Tz, local: NaiveDateTime) -> i64 {
+    let utc = match timezone.from_local_datetime(&local) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time.naive_utc(),
+        LocalResult::None => {
+            local
+                - timezone
+                    .offset_from_utc_datetime(&(local - Duration::days(1)))
+                    .fix()
+        }
+    };
+    utc.timestamp_micros() - PG_EPOCH_UNIX_MICROS
+}
+
+/// Expands the first window into one window per day the rule runs on, up to `until`. Every
+/// occurrence keeps the first one's wall-clock start and end in the rule's timezone, so its
+/// timestamps move by an hour when the clocks change.
+pub fn expand_recurrence(
+    start_time: i64,
+    end_time: i64,
+    rule: &RecurrenceRule,
+) -> Result<Vec<(i64, i64)>, AppError> {
+    check_time_range(start_time, end_time)?;
+    if end_time - start_time > MICROS_PER_DAY {
+        return Err(recurrence_error(
+            "A recurring order can last at most one day",
+        ));
+    }
+    if rule.until <= start_time {
+        return Err(recurrence_error(
+            "Recurrence must end after the first order starts",
+        ));
+    }
+    if rule.until > MAX_ORDER_TIME {
+        return Err(recurrence_error("Recurrence must end before the year 2100"));
+    }
+
+    let first_start = local_time_of(rule.timezone, start_time)?;
+    let length = local_time_of(rule.timezone, end_time)? - first_start;
+
+    let weekdays = match rule.frequency {
+        RecurrenceFrequency::Daily => None,
+        RecurrenceFrequency::Weekly if rule.weekdays.is_empty() => {
+            Some(vec![first_start.weekday()])
+        }
+        RecurrenceFrequency::Weekly => Some(rule.weekdays.clone()),
+    };
+
+    let mut occurrences = Vec::new();
+    let mut day = first_start.date();
+    loop {
+        let local_start = day.and_time(first_start.time());
+        let occurrence_start = pg_time_of(rule.timezone, local_start);
+        if occurrence_start >= rule.until {
+            break;
+        }
+
+        let runs = match &weekdays {
+            Some(weekdays) => weekdays.contains(&day.weekday()),
+            None => true,
+        };
+        if runs {
+            if occurrences.len() == MAX_RECURRENCE_OCCURRENCES {
+                return Err(AppError::new(
+                    Some(format!(
+                        "Recurrence can have at most {} occurrences",
+                        MAX_RECURRENCE_OCCURRENCES
+                    )),
+                    None,
+                    AppErrorType::ValidationError,
+                ));
+            }
+
+            let mut occurrence_end = pg_time_of(rule.timezone, local_start + length);
+            // A window inside the hour skipped when the clocks go forward keeps its length.
+            if occurrence_end <= occurrence_start {
+                occurrence_end = occurrence_start + end_time - start_time;
+            }
+            occurrences.push((occurrence_start, occurrence_end));
+        }
+
+        day = day
+            .succ_opt()
+            .ok_or_else(|| recurrence_error("Order time is out of range"))?;
+    }
+
+    if occurrences.is_empty() {
+        return Err(recurrence_error(
+            "Recurrence has no occurrences before it ends",
+        ));
+    }
+
+    Ok(occurrences)
+}
+
+/// Prices every occurrence and marks the ones that are already booked.
+fn preview_recurrence(
+    conn: &mut PgConnection,
+    screen_id: Uuid,
+    start_time: i64,
+    end_time: i64,
+    rule: &RecurrenceRule,
+    include_pending: bool,
+    billing_unit: BillingUnit,
+) -> Result<RecurrencePreview, AppError> {
+    let mut occurrences = Vec::new();
+    for (occurrence_start, occurrence_end) in expand_recurrence(start_time, end_time, rule)? {
+        let quote = quote_ad_order(
+            conn,
+            screen_id,
+            occurrence_start,
+            occurrence_end,
+            billing_unit,
+        )?;
+        let is_available = find_conflicting_order(
+            conn,
+            screen_id,
+            PgTimestamp(occurrence_start),
+            PgTimestamp(occurrence_end),
+            include_pending,
+        )?
+        .is_none();
+
+        occurrences.push(RecurrenceOccurrence {
+            start_time: occurrence_start,
+            end_time: occurrence_end,
+            price: quote.price,
+            is_available,
+        });
+    }
+
+    let available: Vec<&RecurrenceOccurrence> = occurrences
+        .iter()
+        .filter(|occurrence| occurrence.is_available)
+        .collect();
+
+    Ok(RecurrencePreview {
+        screen_id,
+        available_count: available.len(),
+        available_price: available.iter().map(|occurrence| occurrence.price).sum(),
+        occurrences,
+    })
+}
+
+/// Loads the order together with the business that owns its screen, making sure the caller is
+/// that business. Admins may act on any order, but only when they ask for it explicitly.
+fn find_business_ad_order(
+    conn: &mut PgConnection,
+    ad_order_id: Uuid,
+    business_id: Uuid,
+    admin_override: bool,
+) -> Result<(AdOrder, Uuid), AppError> {
+    let wrapped_ad_order: Option<(AdOrder, Uuid)> = ad_orders
+        .inner_join(screens)
+        .filter(order_id_column.eq(ad_order_id))
+        .select((AdOrder::as_select(), screen_business_id_column))
+        .first(conn)
+        .optional()?;
+
+    match wrapped_ad_order {
+        Some((ad_order, owner_id)) if owner_id == business_id || admin_override => {
+            Ok((ad_order, owner_id))
+        }
+        Some(_) => Err(AppError::new(
+            Some("Ad order belongs to another business".to_string()),
+            None,
+            AppErrorType::ForbiddenError,
+        )),
+        None => Err(AppError::new(
+            Some("Ad order not found".to_string()),
+            None,
+            AppErrorType::NotFoundError,
+        )),
+    }
+}
+
+/// Returns the ids of the given orders that are covered by an active payment.
+fn find_paid_order_ids(conn: &mut PgConnection, order_ids: Vec<Uuid>) -> QueryResult<Vec<Uuid>> {
+    payments
+        .filter(payment_order_id_column.eq_any(order_ids))
+        .filter(payment_status_column.eq_any(PaymentStatus::active()))
+        .select(payment_order_id_column)
+        .load(conn)
+}
+
+fn check_transition(ad_order: &AdOrder, next: AdOrderStatus) -> Result<(), AppError> {
+    let current = AdOrderStatus::from_str(&ad_order.status)
+        .map_err(|err| AppError::new(None, Some(err), AppErrorType::SomethingWentWrong))?;
+
+    if !current.can_transition_to(next) {
+        return Err(AppError::new(
+            Some(format!("Ad order cannot move from {} to {}", current, next)),
+            None,
+            AppErrorType::StatusTransitionError,
+        ));
+    }
+    Ok(())
+}
+
+/// Moves the order to `next` if its state machine allows it. The row is only updated while it
+/// still has the status that was read, so two concurrent transitions cannot both succeed.
+pub fn transition_ad_order(
+    conn: &mut PgConnection,
+    ad_order: &AdOrder,
+    next: AdOrderStatus,
+) -> Result<(), AppError> {
+    check_transition(ad_order, next)?;
+
+    let updated = diesel::update(
+        ad_orders
+            .filter(order_id_column.eq(ad_order.ad_order_id))
+            .filter(ad_order_status_column.eq(&ad_order.status)),
+    )
+    .set(ad_order_status_column.eq(next.to_string()))
+    .execute(conn)?;
+
+    if updated == 0 {
+        return Err(AppError::new(
+            Some("Ad order was changed by another request".to_string()),
+            None,
+            AppErrorType::StatusTransitionError,
+        ));
+    }
+    Ok(())
+}
+
+/// Fails for an order whose window is over, since its screen time can no longer be sold.
+pub fn check_not_ended(ad_order: &AdOrder) -> Result<(), AppError> {
+    if ad_order.end_time.0 <= current_pg_timestamp().0 {
+        return Err(AppError::new(
+            Some("Ad order has already ended".to_string()),
+            None,
+            AppErrorType::ValidationError,
+        ));
+    }
+    Ok(())
+}
+
+pub fn booking_conflict() -> AppError {
+    AppError::new(
+        Some("Screen is already booked for this time".to_string()),
+        None,
+        AppErrorType::BookingConflictError,
+    )
+}
+
 impl Handler<CreateAdOrder> for DbActor {
-    type Result = Result<(), AppError>;
+    type Result = Result<Uuid, AppError>;
 
     fn handle(&mut self, msg: CreateAdOrder, _: &mut Self::Context) -> Self::Result {
         let sub_log = msg.logger.new(o!("handle" => "create_ad_order"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
-        let ad: Ad = ads.find(msg.ad_id).first::<Ad>(&mut conn)?;
+        let owner_id = check_ad_owner(&mut conn, msg.ad_id, msg.user_id, msg.admin_override)?;
+        check_ad_bookable(&mut conn, msg.ad_id)?;
 
-        if ad.status == AdStatus::Unverified.to_string() {
-            let message = Some("Ad is unverified".to_string());
-            return Err(AppError::new(
-                message,
-                None,
-                AppErrorType::UnverifiedAdError,
-            ));
-        } else if ad.status == AdStatus::Rejected.to_string() {
-            let message = Some("Ad is rejected".to_string());
-            return Err(AppError::new(message, None, AppErrorType::RejectedAdError));
+        let quote = quote_ad_order(
+            &mut conn,
+            msg.screen_id,
+            msg.start_time,
+            msg.end_time,
+            msg.billing_unit,
+        )?;
+
+        if let Some(price) = msg.price {
+            if (price - quote.price).abs() > PRICE_TOLERANCE {
+                return Err(AppError::new(
+                    Some(format!(
+                        "Ad order price does not match the quoted price of {:.2}",
+                        quote.price
+                    )),
+                    None,
+                    AppErrorType::ValidationError,
+                ));
+            }
         }
 
         let start_time = PgTimestamp(msg.start_time);
         let end_time = PgTimestamp(msg.end_time);
 
+        if find_conflicting_order(
+            &mut conn,
+            msg.screen_id,
+            start_time,
+            end_time,
+            msg.include_pending,
+        )?
+        .is_some()
+        {
+            return Err(booking_conflict());
+        }
+
         let new_ad_order = AdOrder {
             ad_order_id: Uuid::new_v4(),
             start_time,
             end_time,
-            price: msg.price,
-            is_rejected: true,
+            price: quote.price,
             ad_id: msg.ad_id,
             screen_id: msg.screen_id,
+            status: AdOrderStatus::Pending.to_string(),
+            campaign_id: None,
+            offline_flagged_at: None,
         };
 
-        diesel::insert_into(ad_orders)
-            .values(new_ad_order)
-            .get_result::<AdOrder>(&mut conn)?;
+        let ad_order = conn.transaction::<_, AppError, _>(|conn| {
+            let ad_order = diesel::insert_into(ad_orders)
+                .values(new_ad_order)
+                .get_result::<AdOrder>(conn)?;
 
-        Ok(())
+            if owner_id != msg.user_id {
+                record_admin_override(
+                    conn,
+                    msg.user_id,
+                    AuditAction::CreateAdOrder,
+                    ad_order.ad_order_id,
+                    owner_id,
+                )?;
+            }
+
+            Ok(ad_order)
+        })?;
+
+        Ok(ad_order.ad_order_id)
+    }
+}
+
+impl Handler<PreviewRecurringAdOrder> for DbActor {
+    type Result = Result<RecurrencePreview, AppError>;
+
+    fn handle(&mut self, msg: PreviewRecurringAdOrder, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "preview_recurring_ad_order"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        preview_recurrence(
+            &mut conn,
+            msg.screen_id,
+            msg.start_time,
+            msg.end_time,
+            &msg.recurrence,
+            msg.include_pending,
+            msg.billing_unit,
+        )
+    }
+}
+
+impl Handler<CreateRecurringAdOrder> for DbActor {
+    type Result = Result<RecurringAdOrders, AppError>;
+
+    fn handle(&mut self, msg: CreateRecurringAdOrder, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "create_recurring_ad_order"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let owner_id = check_ad_owner(&mut conn, msg.ad_id, msg.user_id, msg.admin_override)?;
+        check_ad_bookable(&mut conn, msg.ad_id)?;
+
+        conn.transaction::<_, AppError, _>(|conn| {
+            let preview = preview_recurrence(
+                conn,
+                msg.screen_id,
+                msg.start_time,
+                msg.end_time,
+                &msg.recurrence,
+                msg.include_pending,
+                msg.billing_unit,
+            )?;
+
+            let unavailable_count = preview.occurrences.len() - preview.available_count;
+            if preview.available_count == 0 || (unavailable_count > 0 && !msg.skip_unavailable) {
+                return Err(AppError::new(
+                    Some(format!(
+                        "{} of {} occurrences are already booked",
+                        unavailable_count,
+                        preview.occurrences.len()
+                    )),
+                    None,
+                    AppErrorType::BookingConflictError,
+                ));
+            }
+
+            if let Some(price) = msg.price {
+                if (price - preview.available_price).abs() > PRICE_TOLERANCE {
+                    return Err(AppError::new(
+                        Some(format!(
+                            "Ad order price does not match the quoted price of {:.2}",
+                            preview.available_price
+                        )),
+                        None,
+                        AppErrorType::ValidationError,
+                    ));
+                }
+            }
+
+            let (available, skipped): (Vec<_>, Vec<_>) = preview
+                .occurrences
+                .into_iter()
+                .partition(|occurrence| occurrence.is_available);
+
+            let new_ad_orders: Vec<AdOrder> = available
+                .iter()
+                .map(|occurrence| AdOrder {
+                    ad_order_id: Uuid::new_v4(),
+                    start_time: PgTimestamp(occurrence.start_time),
+                    end_time: PgTimestamp(occurrence.end_time),
+                    price: occurrence.price,
+                    ad_id: msg.ad_id,
+                    screen_id: msg.screen_id,
+                    status: AdOrderStatus::Pending.to_string(),
+                    campaign_id: None,
+                    offline_flagged_at: None,
+                })
+                .collect();
+            let booked_orders = diesel::insert_into(ad_orders)
+                .values(new_ad_orders)
+                .get_results::<AdOrder>(conn)?;
+
+            if owner_id != msg.user_id {
+                for ad_order in booked_orders.iter() {
+                    record_admin_override(
+                        conn,
+                        msg.user_id,
+                        AuditAction::CreateAdOrder,
+                        ad_order.ad_order_id,
+                        owner_id,
+                    )?;
+                }
+            }
+
+            Ok(RecurringAdOrders {
+                order_ids: booked_orders
+                    .iter()
+                    .map(|ad_order| ad_order.ad_order_id)
+                    .collect(),
+                total_price: preview.available_price,
+                skipped,
+            })
+        })
+    }
+}
+
+impl Handler<GetAdOrderQuote> for DbActor {
+    type Result = Result<AdOrderQuote, AppError>;
+
+    fn handle(&mut self, msg: GetAdOrderQuote, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_ad_order_quote"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        quote_ad_order(
+            &mut conn,
+            msg.screen_id,
+            msg.start_time,
+            msg.end_time,
+            msg.billing_unit,
+        )
     }
 }
 
@@ -128,6 +853,20 @@ impl Handler<GetBusinessAdOrders> for DbActor {
             .filter(screen_business_id_column.eq(msg.business_id))
             .load::<(Ad, User, Screen, Address, AdOrder)>(&mut conn)?;
 
+        let order_ids: Vec<Uuid> = ad_orders_data
+            .iter()
+            .map(|(_, _, _, _, ad_order)| ad_order.ad_order_id)
+            .collect();
+
+        let paid_order_ids = find_paid_order_ids(&mut conn, order_ids)?;
+        let renditions = find_renditions(
+            &mut conn,
+            ad_orders_data
+                .iter()
+                .flat_map(|(ad, client, _, _, _)| [ad.img_url.clone(), client.img_url.clone()])
+                .collect(),
+        )?;
+
         let ad_orders_all_data = ad_orders_data
             .into_iter()
             .map(|(ad, client, screen, address, ad_order)| AdOrderAllData {
@@ -135,10 +874,18 @@ impl Handler<GetBusinessAdOrders> for DbActor {
                 start_time: ad_order.start_time.0,
                 end_time: ad_order.end_time.0,
                 price: ad_order.price,
-                is_rejected: ad_order.is_rejected,
+                status: ad_order.status,
+                is_paid: paid_order_ids.contains(&ad_order.ad_order_id),
+                offline_flagged_at: ad_order.offline_flagged_at.map(|flagged_at| flagged_at.0),
                 address_name: address.address_name,
-                ad,
-                client,
+                ad: AdInfo {
+                    img_renditions: renditions_for(&renditions, &ad.img_url),
+                    ad,
+                },
+                client: UserInfo {
+                    img_renditions: renditions_for(&renditions, &client.img_url),
+                    user: client,
+                },
                 screen,
             })
             .collect();
@@ -147,6 +894,77 @@ impl Handler<GetBusinessAdOrders> for DbActor {
     }
 }
 
+impl Handler<GetUserAdOrders> for DbActor {
+    type Result = Result<Vec<UserAdOrderData>, AppError>;
+
+    fn handle(&mut self, msg: GetUserAdOrders, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_user_ad_orders"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let mut query = ad_orders
+            .inner_join(ads.on(ad_id_column.eq(ad_orders_ad_id_column)))
+            .inner_join(screens.on(screen_id_column.eq(ad_orders_screen_id_column)))
+            .inner_join(addresses.on(address_id_column.eq(screen_address_id_column)))
+            .filter(ads_user_id_column.eq(msg.user_id))
+            .select((
+                Ad::as_select(),
+                Screen::as_select(),
+                Address::as_select(),
+                AdOrder::as_select(),
+            ))
+            .order(start_time_column.desc())
+            .into_boxed();
+
+        if let Some(statuses) = msg.statuses {
+            let statuses: Vec<String> = statuses.iter().map(|status| status.to_string()).collect();
+            query = query.filter(ad_order_status_column.eq_any(statuses));
+        }
+        if let Some(from) = msg.from {
+            query = query.filter(end_time_column.gt(PgTimestamp(from)));
+        }
+        if let Some(to) = msg.to {
+            query = query.filter(start_time_column.lt(PgTimestamp(to)));
+        }
+
+        let ad_orders_data = query.load::<(Ad, Screen, Address, AdOrder)>(&mut conn)?;
+
+        let order_ids: Vec<Uuid> = ad_orders_data
+            .iter()
+            .map(|(_, _, _, ad_order)| ad_order.ad_order_id)
+            .collect();
+
+        let paid_order_ids = find_paid_order_ids(&mut conn, order_ids)?;
+        let renditions = find_renditions(
+            &mut conn,
+            ad_orders_data
+                .iter()
+                .map(|(ad, _, _, _)| ad.img_url.clone())
+                .collect(),
+        )?;
+
+        let user_ad_orders = ad_orders_data
+            .into_iter()
+            .map(|(ad, screen, address, ad_order)| UserAdOrderData {
+                order_id: ad_order.ad_order_id,
+                start_time: ad_order.start_time.0,
+                end_time: ad_order.end_time.0,
+                price: ad_order.price,
+                status: ad_order.status,
+                is_paid: paid_order_ids.contains(&ad_order.ad_order_id),
+                offline_flagged_at: ad_order.offline_flagged_at.map(|flagged_at| flagged_at.0),
+                address_name: address.address_name,
+                ad: AdInfo {
+                    img_renditions: renditions_for(&renditions, &ad.img_url),
+                    ad,
+                },
+                screen,
+            })
+            .collect();
+
+        Ok(user_ad_orders)
+    }
+}
+
 impl Handler<ApproveAdOrder> for DbActor {
     type Result = Result<(), AppError>;
 
@@ -154,47 +972,38 @@ impl Handler<ApproveAdOrder> for DbActor {
         let sub_log = msg.logger.new(o!("handle" => "approve_ad_order"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
-        let wrapped_ad_order: Option<AdOrder> = ad_orders
-            .filter(order_id_column.eq(msg.ad_order_id))
-            .first(&mut conn)
-            .optional()?;
+        let (ad_order, business_id) = find_business_ad_order(
+            &mut conn,
+            msg.ad_order_id,
+            msg.business_id,
+            msg.admin_override,
+        )?;
 
-        let ad_order = match wrapped_ad_order {
-            Some(ad_order) => {
-                if !ad_order.is_rejected {
-                    return Err(AppError::new(
-                        Some("Add order already approved".to_string()),
-                        None,
-                        AppErrorType::RejectedAdError,
-                    ));
-                }
-                ad_order
-            }
+        check_transition(&ad_order, AdOrderStatus::Approved)?;
+        check_not_ended(&ad_order)?;
+
+        let payment = match find_active_payment(&mut conn, ad_order.ad_order_id)? {
+            Some(payment) => payment,
             None => {
                 return Err(AppError::new(
-                    Some("Add order not found".to_string()),
+                    Some("Ad order is awaiting payment".to_string()),
                     None,
-                    AppErrorType::NotFoundError,
+                    AppErrorType::PaymentError,
                 ));
             }
         };
 
-        let wrapped_business_id: Option<Uuid> = screens
-            .filter(screen_id_column.eq(ad_order.screen_id))
-            .inner_join(businesses)
-            .select(business_id_column)
-            .first(&mut conn)
-            .optional()?;
-
-        let business_id = if wrapped_business_id.is_none() {
-            return Err(AppError::new(
-                Some("Business id not found".to_string()),
-                None,
-                AppErrorType::NotFoundError,
-            ));
-        } else {
-            wrapped_business_id.unwrap()
-        };
+        if find_conflicting_order(
+            &mut conn,
+            ad_order.screen_id,
+            ad_order.start_time,
+            ad_order.end_time,
+            false,
+        )?
+        .is_some()
+        {
+            return Err(booking_conflict());
+        }
 
         let new_income = Income {
             income_id: Uuid::new_v4(),
@@ -203,18 +1012,37 @@ impl Handler<ApproveAdOrder> for DbActor {
             ad_order_id: ad_order.ad_order_id,
         };
 
-        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        conn.transaction::<_, AppError, _>(|conn| {
             diesel::insert_into(incomes)
                 .values(new_income)
                 .get_result::<Income>(conn)?;
 
-            diesel::update(ad_orders.filter(order_id_column.eq(msg.ad_order_id)))
-                .set(is_rejected_column.eq(false))
-                .execute(conn)?;
+            transition_ad_order(conn, &ad_order, AdOrderStatus::Approved)?;
+
+            if business_id != msg.business_id {
+                record_admin_override(
+                    conn,
+                    msg.business_id,
+                    AuditAction::ApproveAdOrder,
+                    ad_order.ad_order_id,
+                    business_id,
+                )?;
+            }
 
             Ok(())
         })?;
 
+        // The approval stands even if the capture fails: the payment stays Authorized and is
+        // captured by the next `CaptureApprovedPayments`.
+        if payment.status == PaymentStatus::Authorized.to_string() {
+            if let Err(err) = capture_payment(&mut conn, &payment, msg.payment_provider.as_ref()) {
+                warn!(
+                    sub_log,
+                    "Failed to capture payment {}: {}", payment.payment_id, err
+                );
+            }
+        }
+
         Ok(())
     }
 }
@@ -226,34 +1054,330 @@ impl Handler<RejectAdOrder> for DbActor {
         let sub_log = msg.logger.new(o!("handle" => "reject_ad_order"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
-        let wrapped_ad_order: Option<AdOrder> = ad_orders
+        let (ad_order, business_id) = find_business_ad_order(
+            &mut conn,
+            msg.ad_order_id,
+            msg.business_id,
+            msg.admin_override,
+        )?;
+
+        conn.transaction::<_, AppError, _>(|conn| {
+            transition_ad_order(conn, &ad_order, AdOrderStatus::Rejected)?;
+
+            if business_id != msg.business_id {
+                record_admin_override(
+                    conn,
+                    msg.business_id,
+                    AuditAction::RejectAdOrder,
+                    ad_order.ad_order_id,
+                    business_id,
+                )?;
+            }
+
+            if ad_order.status == AdOrderStatus::Approved.to_string() {
+                reverse_ad_order_income(conn, ad_order.ad_order_id)?;
+            }
+
+            if let Some(payment) = find_active_payment(conn, msg.ad_order_id)? {
+                release_payment(conn, &payment, msg.payment_provider.as_ref())?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+}
+
+impl Handler<CancelAdOrder> for DbActor {
+    type Result = Result<(), AppError>;
+
+    fn handle(&mut self, msg: CancelAdOrder, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "cancel_ad_order"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let wrapped_ad_order: Option<(AdOrder, Uuid)> = ad_orders
+            .inner_join(ads)
             .filter(order_id_column.eq(msg.ad_order_id))
+            .select((AdOrder::as_select(), ads_user_id_column))
             .first(&mut conn)
             .optional()?;
 
-        match wrapped_ad_order {
-            Some(ad_order) => {
-                if ad_order.is_rejected {
-                    return Err(AppError::new(
-                        Some("Add order already rejected".to_string()),
-                        None,
-                        AppErrorType::RejectedAdError,
-                    ));
-                }
-            }
-            None => {
+        let ad_order = match wrapped_ad_order {
+            Some((ad_order, owner_id)) if owner_id == msg.user_id => ad_order,
+            _ => {
                 return Err(AppError::new(
-                    Some("Add order not found".to_string()),
+                    Some("Ad order not found".to_string()),
                     None,
                     AppErrorType::NotFoundError,
                 ));
             }
         };
 
-        diesel::update(ad_orders.filter(order_id_column.eq(msg.ad_order_id)))
-            .set(is_rejected_column.eq(true))
-            .execute(&mut conn)?;
+        check_transition(&ad_order, AdOrderStatus::Cancelled)?;
 
-        Ok(())
+        let cancellation_window = msg.cancellation_window_hours * BillingUnit::Hour.micros();
+        if current_pg_timestamp().0 > ad_order.start_time.0 - cancellation_window {
+            return Err(AppError::new(
+                Some(format!(
+                    "Ad orders can only be cancelled up to {} hours before they start",
+                    msg.cancellation_window_hours
+                )),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+
+        conn.transaction::<_, AppError, _>(|conn| {
+            transition_ad_order(conn, &ad_order, AdOrderStatus::Cancelled)?;
+
+            if ad_order.status == AdOrderStatus::Approved.to_string() {
+                reverse_ad_order_income(conn, ad_order.ad_order_id)?;
+            }
+
+            if let Some(payment) = find_active_payment(conn, ad_order.ad_order_id)? {
+                release_payment(conn, &payment, msg.payment_provider.as_ref())?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+impl Handler<SyncAdOrderStatuses> for DbActor {
+    type Result = Result<usize, AppError>;
+
+    fn handle(&mut self, msg: SyncAdOrderStatuses, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "sync_ad_order_statuses"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let started = diesel::update(
+            ad_orders
+                .filter(ad_order_status_column.eq(AdOrderStatus::Approved.to_string()))
+                .filter(start_time_column.le(now)),
+        )
+        .set(ad_order_status_column.eq(AdOrderStatus::Running.to_string()))
+        .execute(&mut conn)?;
+
+        let completed = diesel::update(
+            ad_orders
+                .filter(ad_order_status_column.eq(AdOrderStatus::Running.to_string()))
+                .filter(end_time_column.le(now)),
+        )
+        .set(ad_order_status_column.eq(AdOrderStatus::Completed.to_string()))
+        .execute(&mut conn)?;
+
+        Ok(started + completed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveTime, Timelike, Weekday};
+    use chrono_tz::Europe::Berlin;
+
+    const HOUR: i64 = 3_600_000_000;
+
+    fn pg_time(year: i32, month: u32, day: u32, hour: u32) -> i64 {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .and_then(|date| date.and_hms_opt(hour, 0, 0))
+            .unwrap()
+            .timestamp_micros()
+            - PG_EPOCH_UNIX_MICROS
+    }
+
+    fn rule(frequency: RecurrenceFrequency, weekdays: Vec<Weekday>, until: i64) -> RecurrenceRule {
+        RecurrenceRule {
+            frequency,
+            timezone: Tz::UTC,
+            weekdays,
+            until,
+        }
+    }
+
+    #[test]
+    fn leaves_out_an_occurrence_starting_at_until() {
+        let start = pg_time(2026, 10, 5, 10);
+        let daily = |until| rule(RecurrenceFrequency::Daily, vec![], until);
+
+        let occurrences =
+            expand_recurrence(start, start + HOUR, &daily(start + 3 * MICROS_PER_DAY)).unwrap();
+        assert_eq!(occurrences.len(), 3);
+        assert_eq!(
+            occurrences.last(),
+            Some(&(
+                start + 2 * MICROS_PER_DAY,
+                start + 2 * MICROS_PER_DAY + HOUR
+            ))
+        );
+
+        let occurrences =
+            expand_recurrence(start, start + HOUR, &daily(start + 3 * MICROS_PER_DAY + 1)).unwrap();
+        assert_eq!(occurrences.len(), 4);
+    }
+
+    fn berlin_time(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> i64 {
+        let local = NaiveDate::from_ymd_opt(year, month, day)
+            .and_then(|date| date.and_hms_opt(hour, minute, 0))
+            .unwrap();
+        Berlin
+            .from_local_datetime(&local)
+            .unwrap()
+            .timestamp_micros()
+            - PG_EPOCH_UNIX_MICROS
+    }
+
+    fn berlin_daily(until: i64) -> RecurrenceRule {
+        RecurrenceRule {
+            timezone: Berlin,
+            ..rule(RecurrenceFrequency::Daily, vec![], until)
+        }
+    }
+
+    fn wall_clock(time: i64) -> NaiveTime {
+        local_time_of(Berlin, time).unwrap().time()
+    }
+
+    #[test]
+    fn keeps_the_wall_clock_time_across_daylight_saving_changes() {
+        // Berlin moves its clocks forward on 2026-03-29 and back on 2026-10-25.
+        for (month, day, until) in [
+            (3, 27, berlin_time(2026, 4, 1, 0, 0)),
+            (10, 23, berlin_time(2026, 10, 28, 0, 0)),
+        ] {
+            let start = berlin_time(2026, month, day, 9, 0);
+            let occurrences = expand_recurrence(start, start + HOUR, &berlin_daily(until)).unwrap();
+
+            assert_eq!(occurrences.len(), 5);
+            for (occurrence_start, occurrence_end) in &occurrences {
+                assert_eq!(
+                    wall_clock(*occurrence_start),
+                    NaiveTime::from_hms_opt(9, 0, 0).unwrap()
+                );
+                assert_eq!(
+                    wall_clock(*occurrence_end),
+                    NaiveTime::from_hms_opt(10, 0, 0).unwrap()
+                );
+            }
+
+            // The UTC time shifts by an hour once the clocks have changed.
+            let utc_hours: Vec<u32> = occurrences
+                .iter()
+                .map(|(occurrence_start, _)| {
+                    NaiveDateTime::from_timestamp_micros(occurrence_start + PG_EPOCH_UNIX_MICROS)
+                        .unwrap()
+                        .hour()
+                })
+                .collect();
+            let expected = if month == 3 {
+                vec![8, 8, 7, 7, 7]
+            } else {
+                vec![7, 7, 8, 8, 8]
+            };
+            assert_eq!(utc_hours, expected);
+        }
+    }
+
+    #[test]
+    fn moves_occurrences_out_of_a_skipped_hour() {
+        // 02:30 does not exist in Berlin on 2026-03-29, so that occurrence starts at 03:30.
+        let start = berlin_time(2026, 3, 28, 2, 30);
+        let occurrences = expand_recurrence(
+            start,
+            start + HOUR,
+            &berlin_daily(berlin_time(2026, 3, 31, 0, 0)),
+        )
+        .unwrap();
+
+        let starts: Vec<NaiveTime> = occurrences
+            .iter()
+            .map(|(occurrence_start, _)| wall_clock(*occurrence_start))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                NaiveTime::from_hms_opt(2, 30, 0).unwrap(),
+                NaiveTime::from_hms_opt(3, 30, 0).unwrap(),
+                NaiveTime::from_hms_opt(2, 30, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_weekly_rules_on_the_listed_weekdays() {
+        // 2026-10-05 is a Monday.
+        let start = pg_time(2026, 10, 5, 10);
+        let occurrences = expand_recurrence(
+            start,
+            start + HOUR,
+            &rule(
+                RecurrenceFrequency::Weekly,
+                vec![Weekday::Wed, Weekday::Fri],
+                start + 14 * MICROS_PER_DAY,
+            ),
+        )
+        .unwrap();
+
+        let days: Vec<i64> = occurrences
+            .iter()
+            .map(|(occurrence_start, _)| (occurrence_start - start) / MICROS_PER_DAY)
+            .collect();
+        assert_eq!(days, vec![2, 4, 9, 11]);
+    }
+
+    #[test]
+    fn rejects_rules_without_occurrences() {
+        let start = pg_time(2026, 10, 5, 10);
+        let result = expand_recurrence(
+            start,
+            start + HOUR,
+            &rule(
+                RecurrenceFrequency::Weekly,
+                vec![Weekday::Sun],
+                start + 3 * MICROS_PER_DAY,
+            ),
+        );
+
+        assert!(result.is_err_and(|err| matches!(err.error_type, AppErrorType::ValidationError)));
+    }
+
+    #[test]
+    fn rejects_rules_with_too_many_occurrences() {
+        let start = pg_time(2026, 10, 5, 10);
+        let until = start + (MAX_RECURRENCE_OCCURRENCES as i64 + 1) * MICROS_PER_DAY;
+
+        assert!(expand_recurrence(
+            start,
+            start + HOUR,
+            &rule(RecurrenceFrequency::Daily, vec![], until)
+        )
+        .is_err());
+        assert_eq!(
+            expand_recurrence(
+                start,
+                start + HOUR,
+                &rule(RecurrenceFrequency::Daily, vec![], until - MICROS_PER_DAY)
+            )
+            .unwrap()
+            .len(),
+            MAX_RECURRENCE_OCCURRENCES
+        );
+    }
+
+    #[test]
+    fn rejects_windows_too_far_out_to_compute() {
+        let daily = |until| rule(RecurrenceFrequency::Daily, vec![], until);
+
+        for (start, end, until) in [
+            (i64::MIN, 0, HOUR),
+            (0, i64::MAX, i64::MAX),
+            (0, HOUR, i64::MAX),
+        ] {
+            let result = expand_recurrence(start, end, &daily(until));
+            assert!(
+                result.is_err_and(|err| matches!(err.error_type, AppErrorType::ValidationError))
+            );
+        }
     }
 }
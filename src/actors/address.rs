@@ -1,7 +1,11 @@
 use crate::actors::db::{get_pooled_connection, DbActor};
-use crate::errors::AppError;
+use crate::errors::{AppError, AppErrorType};
+use crate::geo::validate_coordinates;
 use crate::models::address::Address;
-use crate::schema::addresses::dsl::{address_id, address_name, addresses};
+use crate::schema::addresses::dsl::{
+    address_id, address_name, addresses, city as city_column, latitude as latitude_column,
+    longitude as longitude_column, region as region_column,
+};
 use actix::{Handler, Message};
 use diesel::expression_methods::ExpressionMethods;
 use diesel::RunQueryDsl;
@@ -13,6 +17,10 @@ use uuid::Uuid;
 pub struct CreateAddress {
     pub name: String,
     pub business_id: Uuid,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub city: Option<String>,
+    pub region: Option<String>,
     pub logger: Logger,
 }
 
@@ -21,6 +29,10 @@ pub struct CreateAddress {
 pub struct UpdateAddress {
     pub id: Uuid,
     pub name: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub city: Option<String>,
+    pub region: Option<String>,
     pub logger: Logger,
 }
 
@@ -30,6 +42,19 @@ pub struct GetAllAddresses {
     pub logger: Logger,
 }
 
+/// Coordinates are optional, but an address can't have only one of them.
+fn check_coordinates(latitude: Option<f64>, longitude: Option<f64>) -> Result<(), AppError> {
+    match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => validate_coordinates(latitude, longitude),
+        (None, None) => Ok(()),
+        _ => Err(AppError::new(
+            Some("Latitude and longitude must be given together".to_string()),
+            None,
+            AppErrorType::ValidationError,
+        )),
+    }
+}
+
 impl Handler<CreateAddress> for DbActor {
     type Result = Result<Address, AppError>;
 
@@ -37,10 +62,16 @@ impl Handler<CreateAddress> for DbActor {
         let sub_log = msg.logger.new(o!("handle" => "create_address"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
+        check_coordinates(msg.latitude, msg.longitude)?;
+
         let new_address = Address {
             address_id: Uuid::new_v4(),
             address_name: msg.name,
             business_id: msg.business_id,
+            latitude: msg.latitude,
+            longitude: msg.longitude,
+            city: msg.city,
+            region: msg.region,
         };
 
         let result = diesel::insert_into(addresses)
@@ -58,9 +89,17 @@ impl Handler<UpdateAddress> for DbActor {
         let sub_log = msg.logger.new(o!("handle" => "update_address"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
+        check_coordinates(msg.latitude, msg.longitude)?;
+
         let updated_address = diesel::update(addresses)
             .filter(address_id.eq(msg.id))
-            .set(address_name.eq(msg.name))
+            .set((
+                address_name.eq(msg.name),
+                latitude_column.eq(msg.latitude),
+                longitude_column.eq(msg.longitude),
+                city_column.eq(msg.city),
+                region_column.eq(msg.region),
+            ))
             .get_result::<Address>(&mut conn)?;
 
         Ok(updated_address)
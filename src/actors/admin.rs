@@ -1,18 +1,22 @@
+use crate::actors::ad_order::screen_has_upcoming_orders;
 use crate::actors::db::{get_pooled_connection, DbActor};
-use crate::errors::AppError;
+use crate::errors::{AppError, AppErrorType};
 use crate::middleware::token::authorize;
 use crate::middleware::token::Role::Admin as AdminRole;
 use crate::models::ad::AdStatus;
 use crate::models::admin::Admin;
+use crate::models::screen::{Screen, ScreenStatus};
 use crate::schema::admin::dsl::{admin as admin_table, admin_name as admin_name_column};
 use crate::schema::ads::dsl::ads;
 use crate::schema::ads::{ad_id as ad_id_column, status as status_column};
+use crate::schema::screens::dsl::screens;
+use crate::schema::screens::status as screen_status_column;
 use actix::{Handler, Message};
 use actix_web_httpauth::extractors::basic::BasicAuth;
 use argonautica::Hasher;
 use diesel::prelude::*;
-use serde::Deserialize;
 use slog::{o, Logger};
+use std::str::FromStr;
 use uuid::Uuid;
 
 #[derive(Message)]
@@ -29,13 +33,6 @@ pub struct AuthorizeAdmin {
     pub basic_auth: BasicAuth,
 }
 
-#[derive(Message, Deserialize)]
-#[rtype(result = "Result<String, AppError>")]
-pub struct ChangeImg {
-    pub user_id: Uuid,
-    pub img_url: String,
-}
-
 #[derive(Message)]
 #[rtype(result = "Result<(), AppError>")]
 pub struct ChangeAdStatus {
@@ -44,6 +41,14 @@ pub struct ChangeAdStatus {
     pub logger: Logger,
 }
 
+#[derive(Message)]
+#[rtype(result = "Result<Screen, AppError>")]
+pub struct ChangeScreenStatus {
+    pub screen_id: Uuid,
+    pub new_status: ScreenStatus,
+    pub logger: Logger,
+}
+
 impl Handler<CreateAdmin> for DbActor {
     type Result = Result<Admin, AppError>;
 
@@ -107,3 +112,54 @@ impl Handler<ChangeAdStatus> for DbActor {
         Ok(())
     }
 }
+
+impl Handler<ChangeScreenStatus> for DbActor {
+    type Result = Result<Screen, AppError>;
+
+    fn handle(&mut self, msg: ChangeScreenStatus, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "change_screen_status"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        conn.transaction::<_, AppError, _>(|conn| {
+            let screen = screens
+                .find(msg.screen_id)
+                .for_update()
+                .first::<Screen>(conn)
+                .optional()?
+                .ok_or_else(|| {
+                    AppError::new(
+                        Some("Screen not found".to_string()),
+                        None,
+                        AppErrorType::NotFoundError,
+                    )
+                })?;
+
+            let current = ScreenStatus::from_str(&screen.status)
+                .map_err(|err| AppError::new(None, Some(err), AppErrorType::SomethingWentWrong))?;
+            if !current.can_transition_to(msg.new_status) {
+                return Err(AppError::new(
+                    Some(format!(
+                        "Screen cannot move from {} to {}",
+                        current, msg.new_status
+                    )),
+                    None,
+                    AppErrorType::StatusTransitionError,
+                ));
+            }
+
+            if msg.new_status.is_withdrawn() && screen_has_upcoming_orders(conn, msg.screen_id)? {
+                return Err(AppError::new(
+                    Some("Screen has upcoming ad orders".to_string()),
+                    None,
+                    AppErrorType::BookingConflictError,
+                ));
+            }
+
+            let updated_screen = diesel::update(screens.find(msg.screen_id))
+                .set(screen_status_column.eq(msg.new_status.to_string()))
+                .get_result::<Screen>(conn)?;
+
+            Ok(updated_screen)
+        })
+    }
+}
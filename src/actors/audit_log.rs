@@ -0,0 +1,94 @@
+use crate::actors::db::{get_pooled_connection, DbActor};
+use crate::db_utils::current_pg_timestamp;
+use crate::errors::{AppError, AppErrorType};
+use crate::models::audit_log::{AuditAction, AuditLog, AuditLogData};
+use crate::schema::audit_logs::dsl::audit_logs;
+use crate::schema::audit_logs::{
+    audit_log_id as audit_log_id_column, created_at as created_at_column,
+};
+use actix::{Handler, Message};
+use diesel::expression_methods::ExpressionMethods;
+use diesel::{PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use slog::{o, Logger};
+use uuid::Uuid;
+
+/// Audit log entries returned when the request does not ask for a page size.
+pub const DEFAULT_AUDIT_LOG_PAGE_SIZE: i64 = 50;
+const MAX_AUDIT_LOG_PAGE_SIZE: i64 = 500;
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<AuditLogData>, AppError>")]
+pub struct GetAuditLogs {
+    pub limit: i64,
+    pub offset: i64,
+    pub logger: Logger,
+}
+
+/// Records that an admin acted on `target_id`, which belongs to `owner_id`. Callers run this in
+/// the same transaction as the action itself.
+pub fn record_admin_override(
+    conn: &mut PgConnection,
+    admin_id: Uuid,
+    action: AuditAction,
+    target_id: Uuid,
+    owner_id: Uuid,
+) -> QueryResult<()> {
+    diesel::insert_into(audit_logs)
+        .values(AuditLog {
+            audit_log_id: Uuid::new_v4(),
+            admin_id,
+            action: action.to_string(),
+            target_id,
+            owner_id,
+            created_at: current_pg_timestamp(),
+        })
+        .execute(conn)?;
+
+    Ok(())
+}
+
+impl Handler<GetAuditLogs> for DbActor {
+    type Result = Result<Vec<AuditLogData>, AppError>;
+
+    fn handle(&mut self, msg: GetAuditLogs, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_audit_logs"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        if !(1..=MAX_AUDIT_LOG_PAGE_SIZE).contains(&msg.limit) {
+            return Err(AppError::new(
+                Some(format!(
+                    "Page size must be between 1 and {}",
+                    MAX_AUDIT_LOG_PAGE_SIZE
+                )),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+        if msg.offset < 0 {
+            return Err(AppError::new(
+                Some("Offset must not be negative".to_string()),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+
+        // The id breaks ties between entries of the same instant, so pages don't overlap.
+        let result = audit_logs
+            .order((created_at_column.desc(), audit_log_id_column.desc()))
+            .limit(msg.limit)
+            .offset(msg.offset)
+            .load::<AuditLog>(&mut conn)?
+            .into_iter()
+            .map(|audit_log| AuditLogData {
+                audit_log_id: audit_log.audit_log_id,
+                admin_id: audit_log.admin_id,
+                action: audit_log.action,
+                target_id: audit_log.target_id,
+                owner_id: audit_log.owner_id,
+                created_at: audit_log.created_at.0,
+            })
+            .collect();
+
+        Ok(result)
+    }
+}
@@ -1,9 +1,11 @@
 use crate::actors::db::{get_pooled_connection, DbActor};
+use crate::actors::media::{find_renditions, renditions_for};
 use crate::errors::AppError;
 use crate::middleware::token::authorize;
 use crate::middleware::token::Role::Business as BusinessRole;
-use crate::models::business::{Business, BusinessInfo};
+use crate::models::business::{Business, BusinessInfo, BusinessListing};
 use crate::models::category::{BusinessCategory, Category};
+use crate::models::media::Renditions;
 use crate::models::screen::Screen;
 use crate::schema::business_categories::business_id;
 use crate::schema::business_categories::dsl::business_categories;
@@ -23,7 +25,7 @@ use slog::{info, o, Logger};
 use uuid::Uuid;
 
 #[derive(Message)]
-#[rtype(result = "Result<Vec<Business>, AppError>")]
+#[rtype(result = "Result<Vec<BusinessListing>, AppError>")]
 pub struct GetAllBusinesses {
     pub logger: Logger,
 }
@@ -49,14 +51,6 @@ pub struct ChangeBusinessInfo {
     pub logger: Logger,
 }
 
-#[derive(Message)]
-#[rtype(result = "Result<(), AppError>")]
-pub struct ChangeBusinessCategories {
-    pub business_id: Uuid,
-    pub category_ids: Vec<Uuid>,
-    pub logger: Logger,
-}
-
 #[derive(Message)]
 #[rtype(result = "Result<Business, AppError>")]
 pub struct CreateBusiness {
@@ -159,15 +153,28 @@ impl Handler<ChangeImg> for DbActor {
 }
 
 impl Handler<GetAllBusinesses> for DbActor {
-    type Result = Result<Vec<Business>, AppError>;
+    type Result = Result<Vec<BusinessListing>, AppError>;
 
     fn handle(&mut self, msg: GetAllBusinesses, _: &mut Self::Context) -> Self::Result {
         let sub_log = msg.logger.new(o!("handle" => "create_businesses"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
         let result = businesses_table.get_results::<Business>(&mut conn)?;
-
-        Ok(result)
+        let renditions = find_renditions(
+            &mut conn,
+            result
+                .iter()
+                .map(|business| business.img_url.clone())
+                .collect(),
+        )?;
+
+        Ok(result
+            .into_iter()
+            .map(|business| BusinessListing {
+                img_renditions: renditions_for(&renditions, &business.img_url),
+                business,
+            })
+            .collect())
     }
 }
 
@@ -180,7 +187,7 @@ impl Handler<GetBusinessCategories> for DbActor {
 
         let result = business_categories
             .inner_join(categories)
-            .filter(business_id.eq(business_id))
+            .filter(business_id.eq(msg.business_id))
             .select((category_id, category_name))
             .get_results::<Category>(&mut conn)?;
 
@@ -209,6 +216,8 @@ impl Handler<GetBusinessesInfo> for DbActor {
                 email: business_data.email,
                 categories: vec![],
                 screens: vec![],
+                img_url: business_data.img_url,
+                img_renditions: Renditions::new(),
             }),
         };
 
@@ -225,6 +234,10 @@ impl Handler<GetBusinessesInfo> for DbActor {
 
             business_info.categories = categories_for_business;
             business_info.screens = screens_for_business;
+            business_info.img_renditions =
+                find_renditions(&mut conn, vec![business_info.img_url.clone()])?
+                    .remove(&business_info.img_url)
+                    .unwrap_or_default();
             return Ok(Some(business_info));
         };
 
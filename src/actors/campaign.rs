@@ -0,0 +1,422 @@
+use crate::actors::ad::check_ad_owner;
+use crate::actors::ad_order::{check_ad_bookable, find_conflicting_order, quote_ad_order};
+use crate::actors::db::{get_pooled_connection, DbActor};
+use crate::actors::payment::PRICE_TOLERANCE;
+use crate::config::BillingUnit;
+use crate::db_utils::current_pg_timestamp;
+use crate::errors::{AppError, AppErrorType};
+use crate::models::ad_order::{AdOrder, AdOrderQuote, AdOrderStatus};
+use crate::models::campaign::{
+    Campaign, CampaignInfo, CampaignOrder, CampaignSlot, CampaignStatus,
+};
+use crate::models::payment::{shared_payment_reference, Payment, PaymentStatus};
+use crate::payment_provider::PaymentProvider;
+use crate::schema::ad_orders::dsl::ad_orders;
+use crate::schema::ad_orders::{
+    campaign_id as order_campaign_id_column, start_time as order_start_time_column,
+};
+use crate::schema::campaigns::dsl::campaigns;
+use crate::schema::campaigns::{
+    campaign_id as campaign_id_column, created_at as campaign_created_at_column,
+    user_id as campaign_user_id_column,
+};
+use crate::schema::payments::dsl::payments;
+use actix::{Handler, Message};
+use diesel::data_types::PgTimestamp;
+use diesel::expression_methods::ExpressionMethods;
+use diesel::{Connection, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl};
+use slog::{o, Logger};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Most slots a single campaign can book.
+const MAX_CAMPAIGN_SLOTS: usize = 500;
+
+#[derive(Message)]
+#[rtype(result = "Result<CampaignInfo, AppError>")]
+pub struct CreateCampaign {
+    pub campaign_name: String,
+    pub ad_id: Uuid,
+    pub user_id: Uuid,
+    pub budget: f64,
+    pub slots: Vec<CampaignSlot>,
+    pub price: Option<f64>,
+    pub include_pending: bool,
+    pub billing_unit: BillingUnit,
+    pub payment_provider: Arc<dyn PaymentProvider>,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<CampaignInfo>, AppError>")]
+pub struct GetUserCampaigns {
+    pub user_id: Uuid,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<CampaignInfo, AppError>")]
+pub struct GetCampaign {
+    pub campaign_id: Uuid,
+    pub user_id: Uuid,
+    pub logger: Logger,
+}
+
+fn campaign_error(message: String) -> AppError {
+    AppError::new(Some(message), None, AppErrorType::ValidationError)
+}
+
+/// Returns the slots that overlap another slot of the same campaign on the same screen.
+fn find_overlapping_slots(slots: &[CampaignSlot]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..slots.len()).collect();
+    order.sort_by_key(|&i| (slots[i].screen_id, slots[i].start_time));
+
+    let mut overlapping = Vec::new();
+    // Latest end among the slots already seen on the current screen.
+    let mut latest_end: Option<(Uuid, i64)> = None;
+    for (position, &i) in order.iter().enumerate() {
+        let slot = &slots[i];
+        let overlaps_earlier = latest_end
+            .is_some_and(|(screen_id, end)| screen_id == slot.screen_id && slot.start_time < end);
+        // Later slots are sorted by start, so the next one is the first that could overlap.
+        let overlaps_later = order.get(position + 1).is_some_and(|&next| {
+            slots[next].screen_id == slot.screen_id && slots[next].start_time < slot.end_time
+        });
+        if overlaps_earlier || overlaps_later {
+            overlapping.push(i);
+        }
+
+        latest_end = match latest_end {
+            Some((screen_id, end)) if screen_id == slot.screen_id => {
+                Some((screen_id, end.max(slot.end_time)))
+            }
+            _ => Some((slot.screen_id, slot.end_time)),
+        };
+    }
+    overlapping
+}
+
+/// Prices every slot and checks that each one can still be booked. Nothing is booked unless
+/// every slot is free, so all unavailable slots are reported together.
+pub fn quote_campaign_slots(
+    conn: &mut PgConnection,
+    slots: &[CampaignSlot],
+    include_pending: bool,
+    billing_unit: BillingUnit,
+) -> Result<Vec<AdOrderQuote>, AppError> {
+    let mut quotes = Vec::with_capacity(slots.len());
+    let mut unavailable = find_overlapping_slots(slots);
+
+    for (i, slot) in slots.iter().enumerate() {
+        quotes.push(quote_ad_order(
+            conn,
+            slot.screen_id,
+            slot.start_time,
+            slot.end_time,
+            billing_unit,
+        )?);
+
+        if find_conflicting_order(
+            conn,
+            slot.screen_id,
+            PgTimestamp(slot.start_time),
+            PgTimestamp(slot.end_time),
+            include_pending,
+        )?
+        .is_some()
+        {
+            unavailable.push(i);
+        }
+    }
+
+    if !unavailable.is_empty() {
+        unavailable.sort();
+        unavailable.dedup();
+        let described: Vec<String> = unavailable
+            .iter()
+            .map(|&i| {
+                format!(
+                    "screen {} from {} to {}",
+                    slots[i].screen_id, slots[i].start_time, slots[i].end_time
+                )
+            })
+            .collect();
+        return Err(AppError::new(
+            Some(format!(
+                "{} of {} slots are not available: {}",
+                unavailable.len(),
+                slots.len(),
+                described.join(", ")
+            )),
+            None,
+            AppErrorType::BookingConflictError,
+        ));
+    }
+
+    Ok(quotes)
+}
+
+/// Books pending orders for the quoted slots under a new campaign. Every order gets its own
+/// payment row on the campaign's authorization, with a reference of its own, so approving,
+/// rejecting or cancelling an order, or a webhook event for its payment, captures or releases
+/// just its share.
+pub fn insert_campaign(
+    conn: &mut PgConnection,
+    campaign: Campaign,
+    quotes: &[AdOrderQuote],
+) -> Result<(Campaign, Vec<AdOrder>), AppError> {
+    let campaign = diesel::insert_into(campaigns)
+        .values(campaign)
+        .get_result::<Campaign>(conn)?;
+
+    let new_ad_orders: Vec<AdOrder> = quotes
+        .iter()
+        .map(|quote| AdOrder {
+            ad_order_id: Uuid::new_v4(),
+            start_time: PgTimestamp(quote.start_time),
+            end_time: PgTimestamp(quote.end_time),
+            price: quote.price,
+            ad_id: campaign.ad_id,
+            screen_id: quote.screen_id,
+            status: AdOrderStatus::Pending.to_string(),
+            campaign_id: Some(campaign.campaign_id),
+            offline_flagged_at: None,
+        })
+        .collect();
+    let booked_orders = diesel::insert_into(ad_orders)
+        .values(new_ad_orders)
+        .get_results::<AdOrder>(conn)?;
+
+    let new_payments: Vec<Payment> = booked_orders
+        .iter()
+        .map(|ad_order| Payment {
+            payment_id: Uuid::new_v4(),
+            price: ad_order.price,
+            user_id: campaign.user_id,
+            ad_order_id: ad_order.ad_order_id,
+            status: PaymentStatus::Authorized.to_string(),
+            provider_reference: shared_payment_reference(
+                &campaign.provider_reference,
+                ad_order.ad_order_id,
+            ),
+        })
+        .collect();
+    diesel::insert_into(payments)
+        .values(new_payments)
+        .execute(conn)?;
+
+    Ok((campaign, booked_orders))
+}
+
+fn campaign_info(campaign: Campaign, campaign_orders: Vec<AdOrder>) -> CampaignInfo {
+    let statuses: Vec<String> = campaign_orders
+        .iter()
+        .map(|ad_order| ad_order.status.clone())
+        .collect();
+    let active_price = campaign_orders
+        .iter()
+        .filter(|ad_order| {
+            ad_order.status != AdOrderStatus::Rejected.to_string()
+                && ad_order.status != AdOrderStatus::Cancelled.to_string()
+        })
+        .map(|ad_order| ad_order.price)
+        .sum();
+
+    CampaignInfo {
+        campaign_id: campaign.campaign_id,
+        campaign_name: campaign.campaign_name,
+        ad_id: campaign.ad_id,
+        budget: campaign.budget,
+        total_price: campaign.total_price,
+        active_price,
+        status: CampaignStatus::from_orders(&statuses).to_string(),
+        created_at: campaign.created_at.0,
+        orders: campaign_orders
+            .into_iter()
+            .map(|ad_order| CampaignOrder {
+                order_id: ad_order.ad_order_id,
+                screen_id: ad_order.screen_id,
+                start_time: ad_order.start_time.0,
+                end_time: ad_order.end_time.0,
+                price: ad_order.price,
+                status: ad_order.status,
+            })
+            .collect(),
+    }
+}
+
+fn load_campaign_orders(
+    conn: &mut PgConnection,
+    campaign_id: Uuid,
+) -> Result<Vec<AdOrder>, AppError> {
+    let campaign_orders = ad_orders
+        .filter(order_campaign_id_column.eq(campaign_id))
+        .order(order_start_time_column.asc())
+        .load::<AdOrder>(conn)?;
+    Ok(campaign_orders)
+}
+
+impl Handler<CreateCampaign> for DbActor {
+    type Result = Result<CampaignInfo, AppError>;
+
+    fn handle(&mut self, msg: CreateCampaign, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "create_campaign"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        if msg.slots.is_empty() || msg.slots.len() > MAX_CAMPAIGN_SLOTS {
+            return Err(campaign_error(format!(
+                "A campaign needs between 1 and {} slots",
+                MAX_CAMPAIGN_SLOTS
+            )));
+        }
+        if !(msg.budget.is_finite() && msg.budget > 0.0) {
+            return Err(campaign_error(
+                "Campaign budget must be a positive amount".to_string(),
+            ));
+        }
+
+        check_ad_owner(&mut conn, msg.ad_id, msg.user_id, false)?;
+        check_ad_bookable(&mut conn, msg.ad_id)?;
+
+        let payment_provider = msg.payment_provider.as_ref();
+        let (campaign, campaign_orders) = conn.transaction::<_, AppError, _>(|conn| {
+            let quotes =
+                quote_campaign_slots(conn, &msg.slots, msg.include_pending, msg.billing_unit)?;
+            let total_price: f64 = quotes.iter().map(|quote| quote.price).sum();
+
+            if let Some(price) = msg.price {
+                if (price - total_price).abs() > PRICE_TOLERANCE {
+                    return Err(campaign_error(format!(
+                        "Campaign price does not match the quoted price of {:.2}",
+                        total_price
+                    )));
+                }
+            }
+            if total_price > msg.budget + PRICE_TOLERANCE {
+                return Err(campaign_error(format!(
+                    "Campaign costs {:.2}, which is over the budget of {:.2}",
+                    total_price, msg.budget
+                )));
+            }
+
+            let campaign_id = Uuid::new_v4();
+            let provider_reference = payment_provider.authorize(campaign_id, total_price)?;
+
+            let new_campaign = Campaign {
+                campaign_id,
+                campaign_name: msg.campaign_name,
+                user_id: msg.user_id,
+                ad_id: msg.ad_id,
+                budget: msg.budget,
+                total_price,
+                provider_reference: provider_reference.clone(),
+                created_at: current_pg_timestamp(),
+            };
+
+            match insert_campaign(conn, new_campaign, &quotes) {
+                Ok(result) => Ok(result),
+                Err(err) => {
+                    payment_provider.void(&provider_reference, total_price)?;
+                    Err(err)
+                }
+            }
+        })?;
+
+        Ok(campaign_info(campaign, campaign_orders))
+    }
+}
+
+impl Handler<GetUserCampaigns> for DbActor {
+    type Result = Result<Vec<CampaignInfo>, AppError>;
+
+    fn handle(&mut self, msg: GetUserCampaigns, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_user_campaigns"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let user_campaigns = campaigns
+            .filter(campaign_user_id_column.eq(msg.user_id))
+            .order(campaign_created_at_column.desc())
+            .load::<Campaign>(&mut conn)?;
+
+        let mut result = Vec::with_capacity(user_campaigns.len());
+        for campaign in user_campaigns {
+            let campaign_orders = load_campaign_orders(&mut conn, campaign.campaign_id)?;
+            result.push(campaign_info(campaign, campaign_orders));
+        }
+
+        Ok(result)
+    }
+}
+
+impl Handler<GetCampaign> for DbActor {
+    type Result = Result<CampaignInfo, AppError>;
+
+    fn handle(&mut self, msg: GetCampaign, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_campaign"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let wrapped_campaign = campaigns
+            .filter(campaign_id_column.eq(msg.campaign_id))
+            .filter(campaign_user_id_column.eq(msg.user_id))
+            .first::<Campaign>(&mut conn)
+            .optional()?;
+
+        match wrapped_campaign {
+            Some(campaign) => {
+                let campaign_orders = load_campaign_orders(&mut conn, campaign.campaign_id)?;
+                Ok(campaign_info(campaign, campaign_orders))
+            }
+            None => Err(AppError::new(
+                Some("Campaign not found".to_string()),
+                None,
+                AppErrorType::NotFoundError,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot(screen_id: Uuid, start_time: i64, end_time: i64) -> CampaignSlot {
+        CampaignSlot {
+            screen_id,
+            start_time,
+            end_time,
+        }
+    }
+
+    fn overlapping(slots: &[CampaignSlot]) -> Vec<usize> {
+        let mut found = find_overlapping_slots(slots);
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn reports_slots_overlapping_a_long_earlier_slot() {
+        let screen = Uuid::new_v4();
+        // The last slot only overlaps the first one, which is not its neighbour once sorted.
+        let slots = [
+            slot(screen, 0, 100),
+            slot(screen, 10, 20),
+            slot(screen, 30, 40),
+        ];
+
+        assert_eq!(overlapping(&slots), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn leaves_out_back_to_back_slots_and_other_screens() {
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let slots = [
+            slot(first, 0, 10),
+            slot(first, 10, 20),
+            slot(second, 5, 15),
+            slot(first, 25, 30),
+            slot(second, 14, 16),
+        ];
+
+        assert_eq!(overlapping(&slots), vec![2, 4]);
+    }
+}
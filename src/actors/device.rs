@@ -0,0 +1,421 @@
+use crate::actors::db::{get_pooled_connection, DbActor};
+use crate::actors::media::find_rendition_ids;
+use crate::actors::screens::find_business_screen;
+use crate::db_utils::current_pg_timestamp;
+use crate::errors::{AppError, AppErrorType};
+use crate::media::canonical_media_id;
+use crate::media::rendition::RenditionSize;
+use crate::models::ad_order::AdOrderStatus;
+use crate::models::device::{
+    DeviceKey, Playlist, PlaylistItem, ScreenDevice, ScreenHealth, ScreenOnlineStatus,
+};
+use crate::models::media::media_url;
+use crate::models::screen::Screen;
+use crate::models::screen_characteristics::ScreenCharacteristics;
+use crate::schema::ad_orders::dsl::ad_orders;
+use crate::schema::ad_orders::{
+    ad_order_id as order_id_column, end_time as order_end_time_column,
+    offline_flagged_at as order_offline_flagged_at_column, screen_id as order_screen_id_column,
+    start_time as order_start_time_column, status as order_status_column,
+};
+use crate::schema::ads::dsl::ads;
+use crate::schema::ads::{
+    ad_id as ad_id_column, ad_name as ad_name_column, img_url as ad_img_url_column,
+};
+use crate::schema::screen_devices::dsl::screen_devices;
+use crate::schema::screen_devices::{
+    api_key_hash as api_key_hash_column, created_at as device_created_at_column,
+    free_storage_bytes as device_free_storage_bytes_column,
+    last_seen_at as device_last_seen_at_column, screen_id as device_screen_id_column,
+    software_version as device_software_version_column,
+};
+use crate::schema::screens::dsl::screens;
+use crate::schema::screens::{
+    business_id as screen_business_id_column, characteristics as screen_characteristics_column,
+    screen_name as screen_name_column,
+};
+use actix::{Handler, Message};
+use diesel::data_types::PgTimestamp;
+use diesel::expression_methods::ExpressionMethods;
+use diesel::upsert::excluded;
+use diesel::{
+    BoolExpressionMethods, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+};
+use sha2::{Digest, Sha256};
+use slog::{o, Logger};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const MICROS_PER_HOUR: i64 = 3_600_000_000;
+/// How far ahead the playlist looks when the device does not ask for a window.
+pub const DEFAULT_PLAYLIST_HOURS: i64 = 24;
+pub const MAX_PLAYLIST_HOURS: i64 = 168;
+const MAX_SOFTWARE_VERSION_LENGTH: usize = 64;
+
+#[derive(Message)]
+#[rtype(result = "Result<DeviceKey, AppError>")]
+pub struct IssueDeviceKey {
+    pub screen_id: Uuid,
+    pub business_id: Uuid,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Option<Uuid>, AppError>")]
+pub struct AuthorizeDevice {
+    pub api_key: String,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Playlist, AppError>")]
+pub struct GetDevicePlaylist {
+    pub screen_id: Uuid,
+    pub hours: i64,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), AppError>")]
+pub struct RecordHeartbeat {
+    pub screen_id: Uuid,
+    pub software_version: String,
+    pub free_storage_bytes: Option<i64>,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<ScreenHealth>, AppError>")]
+pub struct GetBusinessScreenHealth {
+    pub business_id: Uuid,
+    pub offline_after_micros: i64,
+    pub logger: Logger,
+}
+
+/// Flags the running orders of every screen that has been offline for longer than the threshold.
+#[derive(Message)]
+#[rtype(result = "Result<usize, AppError>")]
+pub struct FlagOfflineScreenOrders {
+    pub offline_after_micros: i64,
+    pub logger: Logger,
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn hash_api_key(api_key: &str) -> String {
+    sha256_hex(api_key.as_bytes())
+}
+
+/// Two random v4 UUIDs give 244 random bits.
+fn generate_api_key() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Returns whether the screen's player is online, with the time of its last heartbeat.
+pub fn find_screen_online_status(
+    conn: &mut PgConnection,
+    screen_id: Uuid,
+    offline_after_micros: i64,
+) -> QueryResult<(ScreenOnlineStatus, Option<i64>)> {
+    let device = screen_devices
+        .find(screen_id)
+        .first::<ScreenDevice>(conn)
+        .optional()?;
+
+    Ok(match device {
+        Some(device) => (
+            device.online_status(current_pg_timestamp(), offline_after_micros),
+            device.last_seen_at.map(|last_seen_at| last_seen_at.0),
+        ),
+        None => (ScreenOnlineStatus::Unregistered, None),
+    })
+}
+
+impl Handler<IssueDeviceKey> for DbActor {
+    type Result = Result<DeviceKey, AppError>;
+
+    fn handle(&mut self, msg: IssueDeviceKey, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "issue_device_key"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let screen = find_business_screen(&mut conn, msg.screen_id, msg.business_id)?;
+
+        // Issuing a new key replaces the old one, so a leaked key can be revoked. The heartbeat
+        // of the old device is dropped too, until the new one reports in.
+        let api_key = generate_api_key();
+        diesel::insert_into(screen_devices)
+            .values(ScreenDevice {
+                screen_id: screen.screen_id,
+                api_key_hash: hash_api_key(&api_key),
+                created_at: current_pg_timestamp(),
+                last_seen_at: None,
+                software_version: None,
+                free_storage_bytes: None,
+            })
+            .on_conflict(device_screen_id_column)
+            .do_update()
+            .set((
+                api_key_hash_column.eq(excluded(api_key_hash_column)),
+                device_created_at_column.eq(excluded(device_created_at_column)),
+                device_last_seen_at_column.eq(excluded(device_last_seen_at_column)),
+                device_software_version_column.eq(excluded(device_software_version_column)),
+                device_free_storage_bytes_column.eq(excluded(device_free_storage_bytes_column)),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(DeviceKey {
+            screen_id: screen.screen_id,
+            api_key,
+        })
+    }
+}
+
+impl Handler<AuthorizeDevice> for DbActor {
+    type Result = Result<Option<Uuid>, AppError>;
+
+    fn handle(&mut self, msg: AuthorizeDevice, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "authorize_device"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let screen_id = screen_devices
+            .filter(api_key_hash_column.eq(hash_api_key(&msg.api_key)))
+            .select(device_screen_id_column)
+            .first::<Uuid>(&mut conn)
+            .optional()?;
+
+        Ok(screen_id)
+    }
+}
+
+impl Handler<GetDevicePlaylist> for DbActor {
+    type Result = Result<Playlist, AppError>;
+
+    fn handle(&mut self, msg: GetDevicePlaylist, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_device_playlist"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        if !(1..=MAX_PLAYLIST_HOURS).contains(&msg.hours) {
+            return Err(AppError::new(
+                Some(format!(
+                    "Playlist window must be between 1 and {} hours",
+                    MAX_PLAYLIST_HOURS
+                )),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+
+        let now = current_pg_timestamp();
+        let window_end = PgTimestamp(now.0 + msg.hours * MICROS_PER_HOUR);
+
+        let characteristics: ScreenCharacteristics = screens
+            .find(msg.screen_id)
+            .select(screen_characteristics_column)
+            .first(&mut conn)?;
+        let screen_rendition = characteristics
+            .resolution
+            .as_ref()
+            .and_then(RenditionSize::for_screen)
+            .map(|size| size.name);
+
+        let rows = ad_orders
+            .inner_join(ads)
+            .filter(order_screen_id_column.eq(msg.screen_id))
+            .filter(order_status_column.eq_any(AdOrderStatus::holding()))
+            .filter(order_end_time_column.gt(now))
+            .filter(order_start_time_column.lt(window_end))
+            .order((order_start_time_column.asc(), order_id_column.asc()))
+            .select((
+                order_id_column,
+                ad_id_column,
+                ad_name_column,
+                ad_img_url_column,
+                order_start_time_column,
+                order_end_time_column,
+            ))
+            .load::<(Uuid, Uuid, String, String, PgTimestamp, PgTimestamp)>(&mut conn)?;
+
+        let original_ids: Vec<String> = rows
+            .iter()
+            .filter_map(|(_, _, _, img_url, _, _)| canonical_media_id(img_url))
+            .collect();
+        let rendition_ids = find_rendition_ids(&mut conn, original_ids)?;
+
+        let items = rows
+            .into_iter()
+            .map(
+                |(order_id, ad_id, ad_name, img_url, start_time, end_time)| {
+                    let original_id = canonical_media_id(&img_url).unwrap_or(img_url);
+                    let media_id = screen_rendition
+                        .as_ref()
+                        .and_then(|name| rendition_ids.get(&original_id)?.get(name))
+                        .cloned()
+                        .unwrap_or(original_id);
+
+                    PlaylistItem {
+                        order_id,
+                        ad_id,
+                        ad_name,
+                        media_url: media_url(&media_id),
+                        media_id,
+                        start_time: start_time.0,
+                        end_time: end_time.0,
+                    }
+                },
+            )
+            .collect();
+
+        Ok(Playlist {
+            screen_id: msg.screen_id,
+            items,
+        })
+    }
+}
+
+impl Handler<RecordHeartbeat> for DbActor {
+    type Result = Result<(), AppError>;
+
+    fn handle(&mut self, msg: RecordHeartbeat, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "record_heartbeat"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let software_version = msg.software_version.trim();
+        if software_version.is_empty() || software_version.len() > MAX_SOFTWARE_VERSION_LENGTH {
+            return Err(AppError::new(
+                Some(format!(
+                    "Software version must be between 1 and {} characters",
+                    MAX_SOFTWARE_VERSION_LENGTH
+                )),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+        if msg.free_storage_bytes.is_some_and(|bytes| bytes < 0) {
+            return Err(AppError::new(
+                Some("Free storage can't be negative".to_string()),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+
+        diesel::update(screen_devices.find(msg.screen_id))
+            .set((
+                device_last_seen_at_column.eq(Some(current_pg_timestamp())),
+                device_software_version_column.eq(Some(software_version)),
+                device_free_storage_bytes_column.eq(msg.free_storage_bytes),
+            ))
+            .execute(&mut conn)?;
+
+        Ok(())
+    }
+}
+
+impl Handler<GetBusinessScreenHealth> for DbActor {
+    type Result = Result<Vec<ScreenHealth>, AppError>;
+
+    fn handle(&mut self, msg: GetBusinessScreenHealth, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_business_screen_health"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let business_screens = screens
+            .filter(screen_business_id_column.eq(msg.business_id))
+            .order(screen_name_column.asc())
+            .load::<Screen>(&mut conn)?;
+        let screen_ids: Vec<Uuid> = business_screens
+            .iter()
+            .map(|screen| screen.screen_id)
+            .collect();
+
+        let mut devices: HashMap<Uuid, ScreenDevice> = screen_devices
+            .filter(device_screen_id_column.eq_any(&screen_ids))
+            .load::<ScreenDevice>(&mut conn)?
+            .into_iter()
+            .map(|device| (device.screen_id, device))
+            .collect();
+
+        let mut flagged_orders: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        for (screen_id, order_id) in ad_orders
+            .filter(order_screen_id_column.eq_any(&screen_ids))
+            .filter(order_offline_flagged_at_column.is_not_null())
+            .order(order_start_time_column.desc())
+            .select((order_screen_id_column, order_id_column))
+            .load::<(Uuid, Uuid)>(&mut conn)?
+        {
+            flagged_orders.entry(screen_id).or_default().push(order_id);
+        }
+
+        let now = current_pg_timestamp();
+        let health = business_screens
+            .into_iter()
+            .map(|screen| {
+                let device = devices.remove(&screen.screen_id);
+                let online_status = match &device {
+                    Some(device) => device.online_status(now, msg.offline_after_micros),
+                    None => ScreenOnlineStatus::Unregistered,
+                };
+
+                ScreenHealth {
+                    screen_id: screen.screen_id,
+                    screen_name: screen.screen_name,
+                    status: screen.status,
+                    online_status: online_status.to_string(),
+                    last_seen_at: device
+                        .as_ref()
+                        .and_then(|device| device.last_seen_at)
+                        .map(|last_seen_at| last_seen_at.0),
+                    software_version: device
+                        .as_ref()
+                        .and_then(|device| device.software_version.clone()),
+                    free_storage_bytes: device.and_then(|device| device.free_storage_bytes),
+                    flagged_order_ids: flagged_orders.remove(&screen.screen_id).unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        Ok(health)
+    }
+}
+
+impl Handler<FlagOfflineScreenOrders> for DbActor {
+    type Result = Result<usize, AppError>;
+
+    fn handle(&mut self, msg: FlagOfflineScreenOrders, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "flag_offline_screen_orders"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let now = current_pg_timestamp();
+        let cutoff = PgTimestamp(now.0 - msg.offline_after_micros);
+
+        // Screens without a device aren't monitored, so their orders are never flagged.
+        let offline_screen_ids = screen_devices
+            .filter(
+                device_last_seen_at_column
+                    .lt(cutoff)
+                    .or(device_last_seen_at_column
+                        .is_null()
+                        .and(device_created_at_column.lt(cutoff))),
+            )
+            .select(device_screen_id_column)
+            .load::<Uuid>(&mut conn)?;
+
+        if offline_screen_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let flagged = diesel::update(
+            ad_orders
+                .filter(order_screen_id_column.eq_any(offline_screen_ids))
+                .filter(order_status_column.eq(AdOrderStatus::Running.to_string()))
+                .filter(order_offline_flagged_at_column.is_null()),
+        )
+        .set(order_offline_flagged_at_column.eq(Some(now)))
+        .execute(&mut conn)?;
+
+        Ok(flagged)
+    }
+}
@@ -1,9 +1,10 @@
 use crate::actors::db::{get_pooled_connection, DbActor};
+use crate::actors::media::{find_renditions, renditions_for};
 use crate::diesel::ExpressionMethods;
 use crate::errors::AppError;
-use crate::models::ad::Ad;
+use crate::models::ad::{Ad, AdInfo};
 use crate::models::income::{Income, IncomeAllData};
-use crate::models::user::User;
+use crate::models::user::{User, UserInfo};
 use crate::schema::ad_orders::dsl::ad_orders;
 use crate::schema::ad_orders::{ad_id as order_ad_id_column, ad_order_id as order_id_column};
 use crate::schema::ads::dsl::ads;
@@ -20,6 +21,31 @@ use diesel::{JoinOnDsl, QueryDsl, SelectableHelper};
 use slog::{o, Logger};
 use uuid::Uuid;
 
+/// Books a negative income that cancels out what the business earned from the order, keeping
+/// the original rows for the record.
+pub fn reverse_ad_order_income(conn: &mut PgConnection, ad_order_id: Uuid) -> QueryResult<()> {
+    let order_incomes = incomes
+        .filter(income_order_id_column.eq(ad_order_id))
+        .load::<Income>(conn)?;
+
+    let earned: f64 = order_incomes.iter().map(|income| income.income).sum();
+
+    if let Some(income) = order_incomes.first() {
+        if earned > 0.0 {
+            diesel::insert_into(incomes)
+                .values(Income {
+                    income_id: Uuid::new_v4(),
+                    income: -earned,
+                    business_id: income.business_id,
+                    ad_order_id,
+                })
+                .execute(conn)?;
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<Vec<IncomeAllData>, AppError>")]
 pub struct GetAllIncomes {
@@ -42,12 +68,26 @@ impl Handler<GetAllIncomes> for DbActor {
             .filter(income_business_id_column.eq(msg.business_id))
             .load::<(Income, User, Ad)>(&mut conn)?;
 
+        let renditions = find_renditions(
+            &mut conn,
+            incomes_data
+                .iter()
+                .flat_map(|(_, user, ad)| [user.img_url.clone(), ad.img_url.clone()])
+                .collect(),
+        )?;
+
         let incomes_all_data = incomes_data
             .into_iter()
             .map(|(income, user, ad)| IncomeAllData {
                 price: income.income,
-                client: user,
-                ad,
+                client: UserInfo {
+                    img_renditions: renditions_for(&renditions, &user.img_url),
+                    user,
+                },
+                ad: AdInfo {
+                    img_renditions: renditions_for(&renditions, &ad.img_url),
+                    ad,
+                },
             })
             .collect();
 
@@ -0,0 +1,155 @@
+use crate::actors::db::{get_pooled_connection, DbActor};
+use crate::errors::AppError;
+use crate::media::canonical_media_id;
+use crate::models::media::{media_url, MediaRendition, Renditions};
+use crate::schema::ads::dsl::{ads, img_url as ad_img_url_column};
+use crate::schema::businesses::dsl::{businesses, img_url as business_img_url_column};
+use crate::schema::media_renditions::dsl::media_renditions;
+use crate::schema::media_renditions::{
+    media_id as rendition_original_id_column, rendition as rendition_name_column,
+    rendition_media_id as rendition_media_id_column,
+};
+use crate::schema::users::dsl::{img_url as user_img_url_column, users};
+use actix::{Handler, Message};
+use diesel::expression_methods::{BoolExpressionMethods, ExpressionMethods};
+use diesel::{PgConnection, QueryDsl, QueryResult, RunQueryDsl};
+use slog::{o, Logger};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Message)]
+#[rtype(result = "Result<(), AppError>")]
+pub struct RecordMediaRenditions {
+    pub renditions: Vec<MediaRendition>,
+    pub logger: Logger,
+}
+
+/// Canonical ids of all media that users, businesses and ads refer to, together with the
+/// renditions of that media.
+#[derive(Message)]
+#[rtype(result = "Result<HashSet<String>, AppError>")]
+pub struct GetReferencedMedia {
+    pub logger: Logger,
+}
+
+/// Drops the rendition records of deleted media, whether it was an original or a rendition.
+#[derive(Message)]
+#[rtype(result = "Result<(), AppError>")]
+pub struct ForgetMediaRenditions {
+    pub media_ids: Vec<String>,
+    pub logger: Logger,
+}
+
+/// Returns the media id of every rendition of the given media ids, keyed by the original's media
+/// id and then by rendition name.
+pub fn find_rendition_ids(
+    conn: &mut PgConnection,
+    media_ids: Vec<String>,
+) -> QueryResult<HashMap<String, BTreeMap<String, String>>> {
+    let rows: Vec<(String, String, String)> = media_renditions
+        .filter(rendition_original_id_column.eq_any(media_ids))
+        .select((
+            rendition_original_id_column,
+            rendition_name_column,
+            rendition_media_id_column,
+        ))
+        .get_results(conn)?;
+
+    let mut rendition_ids: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+    for (original_id, name, rendition_id) in rows {
+        rendition_ids
+            .entry(original_id)
+            .or_default()
+            .insert(name, rendition_id);
+    }
+    Ok(rendition_ids)
+}
+
+/// Returns the renditions of each of the given media ids. Media without renditions, such as
+/// videos and uploads made before renditions existed, is left out of the map.
+pub fn find_renditions(
+    conn: &mut PgConnection,
+    media_ids: Vec<String>,
+) -> QueryResult<HashMap<String, Renditions>> {
+    Ok(find_rendition_ids(conn, media_ids)?
+        .into_iter()
+        .map(|(original_id, rendition_ids)| {
+            let urls = rendition_ids
+                .into_iter()
+                .map(|(name, rendition_id)| (name, media_url(&rendition_id)))
+                .collect();
+            (original_id, urls)
+        })
+        .collect())
+}
+
+/// Renditions of one image out of the map returned by `find_renditions`.
+pub fn renditions_for(renditions: &HashMap<String, Renditions>, media_id: &str) -> Renditions {
+    renditions.get(media_id).cloned().unwrap_or_default()
+}
+
+impl Handler<RecordMediaRenditions> for DbActor {
+    type Result = Result<(), AppError>;
+
+    fn handle(&mut self, msg: RecordMediaRenditions, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "record_media_renditions"));
+        let mut conn = get_pooled_connection(&self.0, sub_log)?;
+
+        diesel::insert_into(media_renditions)
+            .values(msg.renditions)
+            .execute(&mut conn)?;
+        Ok(())
+    }
+}
+
+impl Handler<GetReferencedMedia> for DbActor {
+    type Result = Result<HashSet<String>, AppError>;
+
+    fn handle(&mut self, msg: GetReferencedMedia, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_referenced_media"));
+        let mut conn = get_pooled_connection(&self.0, sub_log)?;
+
+        let mut img_urls: Vec<String> = users.select(user_img_url_column).load(&mut conn)?;
+        img_urls.extend(
+            businesses
+                .select(business_img_url_column)
+                .load::<String>(&mut conn)?,
+        );
+        img_urls.extend(ads.select(ad_img_url_column).load::<String>(&mut conn)?);
+
+        let mut referenced: HashSet<String> = img_urls
+            .iter()
+            .filter_map(|url| canonical_media_id(url))
+            .collect();
+
+        let renditions: Vec<(String, String)> = media_renditions
+            .select((rendition_original_id_column, rendition_media_id_column))
+            .load(&mut conn)?;
+        let referenced_renditions: Vec<String> = renditions
+            .into_iter()
+            .filter(|(original_id, _)| referenced.contains(original_id))
+            .map(|(_, rendition_id)| rendition_id)
+            .collect();
+        referenced.extend(referenced_renditions);
+
+        Ok(referenced)
+    }
+}
+
+impl Handler<ForgetMediaRenditions> for DbActor {
+    type Result = Result<(), AppError>;
+
+    fn handle(&mut self, msg: ForgetMediaRenditions, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "forget_media_renditions"));
+        let mut conn = get_pooled_connection(&self.0, sub_log)?;
+
+        diesel::delete(
+            media_renditions.filter(
+                rendition_original_id_column
+                    .eq_any(&msg.media_ids)
+                    .or(rendition_media_id_column.eq_any(&msg.media_ids)),
+            ),
+        )
+        .execute(&mut conn)?;
+        Ok(())
+    }
+}
@@ -2,9 +2,15 @@ pub mod ad;
 pub mod ad_order;
 pub mod address;
 pub mod admin;
+pub mod audit_log;
 pub mod business;
+pub mod campaign;
 pub mod category;
 pub mod db;
+pub mod device;
 pub mod income;
+pub mod media;
+pub mod payment;
+pub mod play_event;
 pub mod screens;
 pub mod user;
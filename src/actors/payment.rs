@@ -0,0 +1,424 @@
+use crate::actors::ad_order::{check_not_ended, transition_ad_order};
+use crate::actors::db::{get_pooled_connection, DbActor};
+use crate::actors::income::reverse_ad_order_income;
+use crate::errors::{AppError, AppErrorType};
+use crate::models::ad_order::{AdOrder, AdOrderStatus};
+use crate::models::payment::{Payment, PaymentStatus};
+use crate::payment_provider::PaymentProvider;
+use crate::schema::ad_orders::ad_order_id as order_id_column;
+use crate::schema::ad_orders::dsl::ad_orders;
+use crate::schema::ad_orders::status as ad_order_status_column;
+use crate::schema::ads::dsl::ads;
+use crate::schema::ads::user_id as ads_user_id_column;
+use crate::schema::payments::dsl::payments;
+use crate::schema::payments::{
+    ad_order_id as payment_order_id_column, payment_id as payment_id_column,
+    provider_reference as payment_provider_reference_column, status as payment_status_column,
+    user_id as payment_user_id_column,
+};
+use actix::{Handler, Message};
+use diesel::dsl::exists;
+use diesel::expression_methods::ExpressionMethods;
+use diesel::{
+    select, Connection, OptionalExtension, PgConnection, QueryDsl, QueryResult, RunQueryDsl,
+    SelectableHelper,
+};
+use slog::{info, o, warn, Logger};
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Payments are compared with order prices to the cent.
+pub const PRICE_TOLERANCE: f64 = 0.005;
+
+#[derive(Message)]
+#[rtype(result = "Result<Payment, AppError>")]
+pub struct CreatePayment {
+    pub user_id: Uuid,
+    pub ad_order_id: Uuid,
+    pub price: f64,
+    pub payment_provider: Arc<dyn PaymentProvider>,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Payment>, AppError>")]
+pub struct GetUserPayments {
+    pub user_id: Uuid,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Payment, AppError>")]
+pub struct RefundPayment {
+    pub payment_id: Uuid,
+    pub user_id: Uuid,
+    pub payment_provider: Arc<dyn PaymentProvider>,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), AppError>")]
+pub struct ApplyPaymentEvent {
+    pub provider_reference: String,
+    pub status: PaymentStatus,
+    pub logger: Logger,
+}
+
+/// Captures the authorized payments of approved, running and completed orders whose capture
+/// failed when they were approved.
+#[derive(Message)]
+#[rtype(result = "Result<usize, AppError>")]
+pub struct CaptureApprovedPayments {
+    pub payment_provider: Arc<dyn PaymentProvider>,
+    pub logger: Logger,
+}
+
+/// Returns true when the order has a payment that has been neither voided nor refunded.
+pub fn is_ad_order_paid(conn: &mut PgConnection, ad_order_id: Uuid) -> QueryResult<bool> {
+    select(exists(
+        payments
+            .filter(payment_order_id_column.eq(ad_order_id))
+            .filter(payment_status_column.eq_any(PaymentStatus::active())),
+    ))
+    .get_result(conn)
+}
+
+pub fn find_active_payment(
+    conn: &mut PgConnection,
+    ad_order_id: Uuid,
+) -> QueryResult<Option<Payment>> {
+    payments
+        .filter(payment_order_id_column.eq(ad_order_id))
+        .filter(payment_status_column.eq_any(PaymentStatus::active()))
+        .first::<Payment>(conn)
+        .optional()
+}
+
+/// Gives the money of an active payment back to the client: an authorization is voided,
+/// a captured payment is refunded. Callers run this inside their own transaction.
+pub fn release_payment(
+    conn: &mut PgConnection,
+    payment: &Payment,
+    payment_provider: &dyn PaymentProvider,
+) -> Result<Payment, AppError> {
+    let new_status = if payment.status == PaymentStatus::Authorized.to_string() {
+        PaymentStatus::Voided
+    } else if payment.status == PaymentStatus::Captured.to_string() {
+        PaymentStatus::Refunded
+    } else {
+        return Err(AppError::new(
+            Some("Payment already released".to_string()),
+            None,
+            AppErrorType::PaymentError,
+        ));
+    };
+
+    let released_payment =
+        diesel::update(payments.filter(payment_id_column.eq(payment.payment_id)))
+            .set(payment_status_column.eq(new_status.to_string()))
+            .get_result::<Payment>(conn)?;
+
+    match new_status {
+        PaymentStatus::Voided => {
+            payment_provider.void(payment.provider_transaction(), payment.price)?
+        }
+        _ => payment_provider.refund(payment.provider_transaction(), payment.price)?,
+    }
+
+    Ok(released_payment)
+}
+
+/// Takes the money of an authorized payment and marks it Captured. Callers run this after their
+/// transaction has committed, so money is never taken for a change that is rolled back.
+pub fn capture_payment(
+    conn: &mut PgConnection,
+    payment: &Payment,
+    payment_provider: &dyn PaymentProvider,
+) -> Result<(), AppError> {
+    payment_provider.capture(payment.provider_transaction(), payment.price)?;
+
+    diesel::update(
+        payments
+            .filter(payment_id_column.eq(payment.payment_id))
+            .filter(payment_status_column.eq(PaymentStatus::Authorized.to_string())),
+    )
+    .set(payment_status_column.eq(PaymentStatus::Captured.to_string()))
+    .execute(conn)?;
+
+    Ok(())
+}
+
+impl Handler<CreatePayment> for DbActor {
+    type Result = Result<Payment, AppError>;
+
+    fn handle(&mut self, msg: CreatePayment, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "create_payment"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let wrapped_ad_order: Option<(AdOrder, Uuid)> = ad_orders
+            .inner_join(ads)
+            .filter(order_id_column.eq(msg.ad_order_id))
+            .select((AdOrder::as_select(), ads_user_id_column))
+            .first(&mut conn)
+            .optional()?;
+
+        let ad_order = match wrapped_ad_order {
+            Some((ad_order, owner_id)) if owner_id == msg.user_id => ad_order,
+            _ => {
+                return Err(AppError::new(
+                    Some("Ad order not found".to_string()),
+                    None,
+                    AppErrorType::NotFoundError,
+                ));
+            }
+        };
+
+        if ad_order.status != AdOrderStatus::Pending.to_string() {
+            return Err(AppError::new(
+                Some(format!(
+                    "Only pending ad orders can be paid, this one is {}",
+                    ad_order.status
+                )),
+                None,
+                AppErrorType::StatusTransitionError,
+            ));
+        }
+
+        check_not_ended(&ad_order)?;
+
+        if is_ad_order_paid(&mut conn, ad_order.ad_order_id)? {
+            return Err(AppError::new(
+                Some("Ad order is already paid".to_string()),
+                None,
+                AppErrorType::PaymentError,
+            ));
+        }
+
+        if (msg.price - ad_order.price).abs() > PRICE_TOLERANCE {
+            return Err(AppError::new(
+                Some("Payment amount does not match the ad order price".to_string()),
+                None,
+                AppErrorType::PaymentError,
+            ));
+        }
+
+        let payment_id = Uuid::new_v4();
+        let provider_reference = msg.payment_provider.authorize(payment_id, ad_order.price)?;
+
+        let new_payment = Payment {
+            payment_id,
+            price: ad_order.price,
+            user_id: msg.user_id,
+            ad_order_id: ad_order.ad_order_id,
+            status: PaymentStatus::Authorized.to_string(),
+            provider_reference: provider_reference.clone(),
+        };
+
+        let result = diesel::insert_into(payments)
+            .values(new_payment)
+            .get_result::<Payment>(&mut conn);
+
+        match result {
+            Ok(payment) => Ok(payment),
+            Err(err) => {
+                msg.payment_provider
+                    .void(&provider_reference, ad_order.price)?;
+                Err(AppError::from(err))
+            }
+        }
+    }
+}
+
+impl Handler<GetUserPayments> for DbActor {
+    type Result = Result<Vec<Payment>, AppError>;
+
+    fn handle(&mut self, msg: GetUserPayments, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_user_payments"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let result = payments
+            .filter(payment_user_id_column.eq(msg.user_id))
+            .get_results::<Payment>(&mut conn)?;
+
+        Ok(result)
+    }
+}
+
+impl Handler<RefundPayment> for DbActor {
+    type Result = Result<Payment, AppError>;
+
+    fn handle(&mut self, msg: RefundPayment, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "refund_payment"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let wrapped_payment: Option<(Payment, AdOrder)> = payments
+            .inner_join(ad_orders)
+            .filter(payment_id_column.eq(msg.payment_id))
+            .filter(payment_user_id_column.eq(msg.user_id))
+            .select((Payment::as_select(), AdOrder::as_select()))
+            .first(&mut conn)
+            .optional()?;
+
+        let (payment, ad_order) = match wrapped_payment {
+            Some(payment) => payment,
+            None => {
+                return Err(AppError::new(
+                    Some("Payment not found".to_string()),
+                    None,
+                    AppErrorType::NotFoundError,
+                ));
+            }
+        };
+
+        // Money for orders that held or used their screen time stays with the business.
+        if AdOrderStatus::holding().contains(&ad_order.status)
+            || ad_order.status == AdOrderStatus::Completed.to_string()
+        {
+            return Err(AppError::new(
+                Some("Payment for an approved ad order cannot be refunded".to_string()),
+                None,
+                AppErrorType::PaymentError,
+            ));
+        }
+
+        conn.transaction(|conn| release_payment(conn, &payment, msg.payment_provider.as_ref()))
+    }
+}
+
+impl Handler<ApplyPaymentEvent> for DbActor {
+    type Result = Result<(), AppError>;
+
+    /// Webhooks can be replayed or arrive out of order, so an event only moves the payment along
+    /// its state machine and an event for the status it already has is ignored. Releasing the
+    /// money also releases the screen time of an approved order.
+    fn handle(&mut self, msg: ApplyPaymentEvent, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "apply_payment_event"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        // Payments made before provider references existed all have an empty one.
+        if msg.provider_reference.is_empty() {
+            return Err(AppError::new(
+                Some("Provider reference is required".to_string()),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+
+        conn.transaction::<_, AppError, _>(|conn| {
+            let wrapped_payment: Option<Payment> = payments
+                .filter(payment_provider_reference_column.eq(&msg.provider_reference))
+                .for_update()
+                .first(conn)
+                .optional()?;
+
+            let payment = match wrapped_payment {
+                Some(payment) => payment,
+                None => {
+                    return Err(AppError::new(
+                        Some("Payment not found".to_string()),
+                        None,
+                        AppErrorType::NotFoundError,
+                    ));
+                }
+            };
+
+            let current = PaymentStatus::from_str(&payment.status)
+                .map_err(|err| AppError::new(None, Some(err), AppErrorType::SomethingWentWrong))?;
+            if current == msg.status {
+                return Ok(());
+            }
+            if !current.can_transition_to(msg.status) {
+                return Err(AppError::new(
+                    Some(format!(
+                        "Payment cannot move from {} to {}",
+                        current, msg.status
+                    )),
+                    None,
+                    AppErrorType::StatusTransitionError,
+                ));
+            }
+
+            diesel::update(payments.filter(payment_id_column.eq(payment.payment_id)))
+                .set(payment_status_column.eq(msg.status.to_string()))
+                .execute(conn)?;
+
+            if matches!(msg.status, PaymentStatus::Voided | PaymentStatus::Refunded) {
+                release_paid_ad_order(conn, payment.ad_order_id, &sub_log)?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Cancels an approved order whose payment the provider released on its side and takes back its
+/// income. Running and completed orders have used their screen time, so the event is rejected
+/// for them; any other order simply stays unpaid.
+fn release_paid_ad_order(
+    conn: &mut PgConnection,
+    ad_order_id: Uuid,
+    logger: &Logger,
+) -> Result<(), AppError> {
+    let ad_order: AdOrder = ad_orders
+        .filter(order_id_column.eq(ad_order_id))
+        .for_update()
+        .first(conn)?;
+
+    if ad_order.status == AdOrderStatus::Running.to_string()
+        || ad_order.status == AdOrderStatus::Completed.to_string()
+    {
+        return Err(AppError::new(
+            Some(format!(
+                "Payment of a {} ad order cannot be released",
+                ad_order.status
+            )),
+            None,
+            AppErrorType::StatusTransitionError,
+        ));
+    }
+
+    if ad_order.status == AdOrderStatus::Approved.to_string() {
+        transition_ad_order(conn, &ad_order, AdOrderStatus::Cancelled)?;
+        reverse_ad_order_income(conn, ad_order_id)?;
+
+        info!(
+            logger,
+            "Cancelled ad order {} after the provider released its payment", ad_order_id
+        );
+    }
+
+    Ok(())
+}
+
+impl Handler<CaptureApprovedPayments> for DbActor {
+    type Result = Result<usize, AppError>;
+
+    fn handle(&mut self, msg: CaptureApprovedPayments, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "capture_approved_payments"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let mut captured_statuses = AdOrderStatus::holding();
+        captured_statuses.push(AdOrderStatus::Completed.to_string());
+
+        let uncaptured_payments = payments
+            .inner_join(ad_orders)
+            .filter(payment_status_column.eq(PaymentStatus::Authorized.to_string()))
+            .filter(ad_order_status_column.eq_any(captured_statuses))
+            .select(Payment::as_select())
+            .load::<Payment>(&mut conn)?;
+
+        let mut captured = 0;
+        for payment in uncaptured_payments {
+            match capture_payment(&mut conn, &payment, msg.payment_provider.as_ref()) {
+                Ok(()) => captured += 1,
+                Err(err) => warn!(
+                    sub_log,
+                    "Failed to capture payment {}: {}", payment.payment_id, err
+                ),
+            }
+        }
+
+        Ok(captured)
+    }
+}
@@ -0,0 +1,276 @@
+use crate::actors::db::{get_pooled_connection, DbActor};
+use crate::db_utils::current_pg_timestamp;
+use crate::errors::{AppError, AppErrorType};
+use crate::models::ad_order::{AdOrder, AdOrderStatus};
+use crate::models::play_event::{
+    DeliveryReport, PlayEvent, PlayEventBatchResult, PlayEventData, RejectedPlayEvent,
+};
+use crate::schema::ad_orders::dsl::ad_orders;
+use crate::schema::ad_orders::{
+    ad_order_id as order_id_column, start_time as order_start_time_column,
+    status as order_status_column,
+};
+use crate::schema::ads::dsl::ads;
+use crate::schema::ads::user_id as ads_user_id_column;
+use crate::schema::play_events::dsl::play_events;
+use crate::schema::play_events::{
+    ad_order_id as play_order_id_column, duration_ms as play_duration_column,
+    played_at as played_at_column,
+};
+use crate::schema::screens::business_id as screen_business_id_column;
+use crate::schema::screens::dsl::screens;
+use actix::{Handler, Message};
+use diesel::data_types::PgTimestamp;
+use diesel::expression_methods::ExpressionMethods;
+use diesel::{PgConnection, QueryDsl, RunQueryDsl, SelectableHelper};
+use slog::{o, Logger};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Most events a device can send in one request.
+const MAX_PLAY_EVENT_BATCH: usize = 1000;
+/// How far ahead of the server's clock a device's clock may be.
+const MAX_CLOCK_SKEW_MICROS: i64 = 5 * 60 * 1_000_000;
+
+#[derive(Message)]
+#[rtype(result = "Result<PlayEventBatchResult, AppError>")]
+pub struct RecordPlayEvents {
+    /// Screen of the device that sent the batch.
+    pub screen_id: Uuid,
+    pub events: Vec<PlayEventData>,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<DeliveryReport>, AppError>")]
+pub struct GetUserDeliveryReports {
+    pub user_id: Uuid,
+    pub logger: Logger,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<DeliveryReport>, AppError>")]
+pub struct GetBusinessDeliveryReports {
+    pub business_id: Uuid,
+    pub logger: Logger,
+}
+
+/// Orders that can have been played: the ones holding their time and the finished ones.
+fn playable_statuses() -> Vec<String> {
+    let mut statuses = AdOrderStatus::holding();
+    statuses.push(AdOrderStatus::Completed.to_string());
+    statuses
+}
+
+/// Checks an event against the order it claims to have played.
+fn check_play_event(
+    event: &PlayEventData,
+    screen_id: Uuid,
+    ad_order: Option<&AdOrder>,
+    now: i64,
+) -> Result<(), &'static str> {
+    if event.screen_id != screen_id {
+        return Err("Event belongs to another screen");
+    }
+    if event.duration_ms <= 0 {
+        return Err("Duration must be positive");
+    }
+    if event.played_at > now + MAX_CLOCK_SKEW_MICROS {
+        return Err("Play is in the future");
+    }
+
+    match ad_order {
+        Some(ad_order) if ad_order.screen_id != screen_id => {
+            Err("Ad order is booked on another screen")
+        }
+        Some(ad_order) if !playable_statuses().contains(&ad_order.status) => {
+            Err("Ad order is not approved")
+        }
+        Some(ad_order)
+            if event.played_at < ad_order.start_time.0
+                || event.played_at >= ad_order.end_time.0 =>
+        {
+            Err("Play is outside of the booked time")
+        }
+        Some(ad_order)
+            if event.played_at + event.duration_ms as i64 * 1000 > ad_order.end_time.0 =>
+        {
+            Err("Play runs past the booked time")
+        }
+        Some(_) => Ok(()),
+        None => Err("Ad order not found"),
+    }
+}
+
+#[derive(Default)]
+struct PlayTotals {
+    play_count: i64,
+    played_ms: i64,
+    first_played_at: Option<i64>,
+    last_played_at: Option<i64>,
+}
+
+/// Sums up the plays of each order. Overlapping and repeated plays are counted, but the played
+/// time of an order never exceeds its booked time.
+fn delivery_reports(
+    conn: &mut PgConnection,
+    orders: Vec<AdOrder>,
+) -> Result<Vec<DeliveryReport>, AppError> {
+    let order_ids: Vec<Uuid> = orders.iter().map(|ad_order| ad_order.ad_order_id).collect();
+
+    let mut plays: HashMap<Uuid, PlayTotals> = HashMap::new();
+    let order_plays = play_events
+        .filter(play_order_id_column.eq_any(order_ids))
+        .select((play_order_id_column, play_duration_column, played_at_column))
+        .load::<(Uuid, i32, PgTimestamp)>(conn)?;
+    for (ad_order_id, duration_ms, played_at) in order_plays {
+        let totals = plays.entry(ad_order_id).or_default();
+        totals.play_count += 1;
+        totals.played_ms += duration_ms as i64;
+        totals.first_played_at = Some(
+            totals
+                .first_played_at
+                .map_or(played_at.0, |first| first.min(played_at.0)),
+        );
+        totals.last_played_at = Some(
+            totals
+                .last_played_at
+                .map_or(played_at.0, |last| last.max(played_at.0)),
+        );
+    }
+
+    let reports = orders
+        .into_iter()
+        .map(|ad_order| {
+            let totals = plays.remove(&ad_order.ad_order_id).unwrap_or_default();
+            let booked_ms = (ad_order.end_time.0 - ad_order.start_time.0) / 1000;
+            let played_ms = totals.played_ms.min(booked_ms);
+            let delivery_ratio = if booked_ms > 0 {
+                played_ms as f64 / booked_ms as f64
+            } else {
+                0.0
+            };
+
+            DeliveryReport {
+                order_id: ad_order.ad_order_id,
+                screen_id: ad_order.screen_id,
+                start_time: ad_order.start_time.0,
+                end_time: ad_order.end_time.0,
+                status: ad_order.status,
+                booked_ms,
+                play_count: totals.play_count,
+                played_ms,
+                delivery_ratio,
+                first_played_at: totals.first_played_at,
+                last_played_at: totals.last_played_at,
+            }
+        })
+        .collect();
+
+    Ok(reports)
+}
+
+impl Handler<RecordPlayEvents> for DbActor {
+    type Result = Result<PlayEventBatchResult, AppError>;
+
+    fn handle(&mut self, msg: RecordPlayEvents, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "record_play_events"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        if msg.events.len() > MAX_PLAY_EVENT_BATCH {
+            return Err(AppError::new(
+                Some(format!(
+                    "A batch can have at most {} events",
+                    MAX_PLAY_EVENT_BATCH
+                )),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+
+        let order_ids: Vec<Uuid> = msg.events.iter().map(|event| event.ad_order_id).collect();
+        let orders: HashMap<Uuid, AdOrder> = ad_orders
+            .filter(order_id_column.eq_any(order_ids))
+            .load::<AdOrder>(&mut conn)?
+            .into_iter()
+            .map(|ad_order| (ad_order.ad_order_id, ad_order))
+            .collect();
+
+        let received_at = current_pg_timestamp();
+        let mut rejected = Vec::new();
+        let mut new_play_events = Vec::new();
+        for event in msg.events {
+            match check_play_event(
+                &event,
+                msg.screen_id,
+                orders.get(&event.ad_order_id),
+                received_at.0,
+            ) {
+                Ok(()) => new_play_events.push(PlayEvent {
+                    play_event_id: event.event_id,
+                    ad_order_id: event.ad_order_id,
+                    screen_id: event.screen_id,
+                    played_at: PgTimestamp(event.played_at),
+                    duration_ms: event.duration_ms,
+                    received_at,
+                }),
+                Err(reason) => rejected.push(RejectedPlayEvent {
+                    event_id: event.event_id,
+                    reason: reason.to_string(),
+                }),
+            }
+        }
+
+        let valid_count = new_play_events.len();
+        let accepted = diesel::insert_into(play_events)
+            .values(new_play_events)
+            .on_conflict_do_nothing()
+            .execute(&mut conn)?;
+
+        Ok(PlayEventBatchResult {
+            accepted,
+            duplicates: valid_count - accepted,
+            rejected,
+        })
+    }
+}
+
+impl Handler<GetUserDeliveryReports> for DbActor {
+    type Result = Result<Vec<DeliveryReport>, AppError>;
+
+    fn handle(&mut self, msg: GetUserDeliveryReports, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_user_delivery_reports"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let orders = ad_orders
+            .inner_join(ads)
+            .filter(ads_user_id_column.eq(msg.user_id))
+            .filter(order_status_column.eq_any(playable_statuses()))
+            .order(order_start_time_column.asc())
+            .select(AdOrder::as_select())
+            .load::<AdOrder>(&mut conn)?;
+
+        delivery_reports(&mut conn, orders)
+    }
+}
+
+impl Handler<GetBusinessDeliveryReports> for DbActor {
+    type Result = Result<Vec<DeliveryReport>, AppError>;
+
+    fn handle(&mut self, msg: GetBusinessDeliveryReports, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg
+            .logger
+            .new(o!("handle" => "get_business_delivery_reports"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        let orders = ad_orders
+            .inner_join(screens)
+            .filter(screen_business_id_column.eq(msg.business_id))
+            .filter(order_status_column.eq_any(playable_statuses()))
+            .order(order_start_time_column.asc())
+            .select(AdOrder::as_select())
+            .load::<AdOrder>(&mut conn)?;
+
+        delivery_reports(&mut conn, orders)
+    }
+}
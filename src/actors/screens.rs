@@ -1,25 +1,45 @@
+use crate::actors::ad_order::{
+    find_booked_screen_ids, find_booked_times, screen_has_orders, screen_has_upcoming_orders,
+};
 use crate::actors::db::{get_pooled_connection, DbActor};
-use crate::errors::AppError;
-use crate::models::screen::{Screen, ScreenData, ScreenDataWithAddress};
+use crate::actors::device::find_screen_online_status;
+use crate::config::BillingUnit;
+use crate::errors::{AppError, AppErrorType};
+use crate::geo::{haversine_km, validate_coordinates, BoundingBox};
+use crate::models::address::Address;
+use crate::models::screen::{
+    AvailabilityInterval, AvailabilitySlot, NearbyScreen, OptimalScreen, OptimalScreenSelection,
+    Screen, ScreenAvailability, ScreenData, ScreenDataWithAddress, ScreenDeletion, ScreenStatus,
+};
+use crate::models::screen_characteristics::{Resolution, ScreenCharacteristics, ScreenFilter};
 use crate::schema::addresses::dsl::addresses;
-use crate::schema::addresses::{address_id, address_name as address_name_column};
+use crate::schema::addresses::{
+    address_id, address_name as address_name_column, business_id as address_business_id_column,
+    latitude as address_latitude_column, longitude as address_longitude_column,
+};
 use crate::schema::business_categories::business_id as business_categories_business_id_column;
 use crate::schema::business_categories::category_id as business_categories_cat_id_column;
 use crate::schema::business_categories::dsl::business_categories;
-use crate::schema::businesses::business_id as business_id_column;
-use crate::schema::businesses::dsl::businesses;
 use crate::schema::screens::dsl::screens;
 use crate::schema::screens::{
     address_id as screen_address_id, business_id as screen_business_id_column,
     characteristics as screen_characteristics_column,
     price_per_time as screen_price_per_time_column, screen_id as screen_screen_id_column,
-    screen_name as screen_name_column, traffic as screen_traffic_column,
+    screen_name as screen_name_column, status as screen_status_column,
+    traffic as screen_traffic_column,
 };
 use actix::{Handler, Message};
-use diesel::expression_methods::ExpressionMethods;
-use diesel::{JoinOnDsl, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::data_types::PgTimestamp;
+use diesel::dsl::{exists, sql};
+use diesel::expression_methods::{BoolExpressionMethods, ExpressionMethods};
+use diesel::pg::Pg;
+use diesel::sql_types::{Bool, Double, Jsonb};
+use diesel::{
+    select, Connection, JoinOnDsl, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
 use slog::{o, Logger};
-use std::cmp::Reverse;
+use std::collections::HashMap;
 use uuid::Uuid;
 
 #[derive(Message)]
@@ -27,10 +47,11 @@ use uuid::Uuid;
 pub struct CreateScreen {
     pub name: String,
     pub price_per_time: f64,
-    pub characteristics: String,
+    pub characteristics: ScreenCharacteristics,
     pub traffic: i32,
     pub business_id: Uuid,
     pub address_id: Uuid,
+    pub status: ScreenStatus,
     pub logger: Logger,
 }
 
@@ -38,16 +59,26 @@ pub struct CreateScreen {
 #[rtype(result = "Result<Screen, AppError>")]
 pub struct UpdateScreen {
     pub id: Uuid,
+    pub business_id: Uuid,
     pub name: String,
     pub price_per_time: f64,
-    pub characteristics: String,
+    pub characteristics: ScreenCharacteristics,
     pub traffic: i32,
     pub logger: Logger,
 }
 
+#[derive(Message)]
+#[rtype(result = "Result<ScreenDeletion, AppError>")]
+pub struct DeleteScreen {
+    pub id: Uuid,
+    pub business_id: Uuid,
+    pub logger: Logger,
+}
+
 #[derive(Message)]
 #[rtype(result = "Result<Vec<Screen>, AppError>")]
 pub struct GetAllScreens {
+    pub filter: ScreenFilter,
     pub logger: Logger,
 }
 
@@ -55,6 +86,7 @@ pub struct GetAllScreens {
 #[rtype(result = "Result<Option<ScreenDataWithAddress>, AppError>")]
 pub struct GetScreenDataById {
     pub screen_id: Uuid,
+    pub offline_after_micros: i64,
     pub logger: Logger,
 }
 
@@ -66,13 +98,93 @@ pub struct GetAllScreensByBusinessId {
 }
 
 #[derive(Message)]
-#[rtype(result = "Result<Vec<Screen>, AppError>")]
+#[rtype(result = "Result<OptimalScreenSelection, AppError>")]
 pub struct GetOptimalScreens {
     pub user_budget: f64,
     pub ad_category_ids: Vec<Uuid>,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub include_pending: bool,
+    pub billing_unit: BillingUnit,
+    pub logger: Logger,
+}
+
+/// Cap on the number of budget steps the knapsack works with. Budgets that would need more
+/// steps at cent precision are split into coarser steps instead.
+const MAX_BUDGET_STEPS: f64 = 10_000.0;
+const MIN_BUDGET_STEP: f64 = 0.01;
+/// Cap on the screens the knapsack packs, which bounds the work of a single request. When more
+/// screens match, the ones with the most value per budget step are kept.
+const MAX_OPTIMAL_CANDIDATES: usize = 2_000;
+
+/// Screen considered by the optimizer, priced and weighted for the whole campaign.
+struct ScreenCandidate {
+    screen: Screen,
+    price: f64,
+    reach: i64,
+    /// Share of the ad's categories that the screen's business covers.
+    relevance: f64,
+    /// What the knapsack maximizes: reach weighted by the matched categories.
+    value: i64,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<ScreenAvailability, AppError>")]
+pub struct GetScreenAvailability {
+    pub screen_id: Uuid,
+    pub from: i64,
+    pub to: i64,
+    pub slot_minutes: Option<i64>,
+    pub include_pending: bool,
+    pub logger: Logger,
+}
+
+/// Upper bound on the slot grid so a wide window cannot blow up the response.
+const MAX_AVAILABILITY_SLOTS: i64 = 2000;
+
+#[derive(Message)]
+#[rtype(result = "Result<Vec<NearbyScreen>, AppError>")]
+pub struct GetNearbyScreens {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_km: f64,
+    pub logger: Logger,
+}
+
+/// Native resolutions of active screens, most common first, at most `limit` of them.
+#[derive(Message)]
+#[rtype(result = "Result<Vec<Resolution>, AppError>")]
+pub struct GetScreenResolutions {
+    pub limit: usize,
     pub logger: Logger,
 }
 
+/// Widest radius a nearby search may cover.
+const MAX_NEARBY_RADIUS_KM: f64 = 500.0;
+
+/// Loads a screen owned by the given business.
+pub fn find_business_screen(
+    conn: &mut PgConnection,
+    screen_id: Uuid,
+    business_id: Uuid,
+) -> Result<Screen, AppError> {
+    let wrapped_screen: Option<Screen> = screens.find(screen_id).first(conn).optional()?;
+
+    match wrapped_screen {
+        Some(screen) if screen.business_id == business_id => Ok(screen),
+        Some(_) => Err(AppError::new(
+            Some("Screen belongs to another business".to_string()),
+            None,
+            AppErrorType::ForbiddenError,
+        )),
+        None => Err(AppError::new(
+            Some("Screen not found".to_string()),
+            None,
+            AppErrorType::NotFoundError,
+        )),
+    }
+}
+
 impl Handler<CreateScreen> for DbActor {
     type Result = Result<Screen, AppError>;
 
@@ -80,6 +192,32 @@ impl Handler<CreateScreen> for DbActor {
         let sub_log = msg.logger.new(o!("handle" => "create_screen"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
+        msg.characteristics.validate()?;
+
+        let wrapped_address_business_id: Option<Uuid> = addresses
+            .find(msg.address_id)
+            .select(address_business_id_column)
+            .first(&mut conn)
+            .optional()?;
+
+        match wrapped_address_business_id {
+            Some(address_business_id) if address_business_id == msg.business_id => {}
+            Some(_) => {
+                return Err(AppError::new(
+                    Some("Address belongs to another business".to_string()),
+                    None,
+                    AppErrorType::ValidationError,
+                ));
+            }
+            None => {
+                return Err(AppError::new(
+                    Some("Address not found".to_string()),
+                    None,
+                    AppErrorType::NotFoundError,
+                ));
+            }
+        }
+
         let new_screen = Screen {
             screen_id: Uuid::new_v4(),
             screen_name: msg.name,
@@ -88,6 +226,7 @@ impl Handler<CreateScreen> for DbActor {
             traffic: msg.traffic,
             address_id: msg.address_id,
             business_id: msg.business_id,
+            status: msg.status.to_string(),
         };
 
         let result = diesel::insert_into(screens)
@@ -102,9 +241,20 @@ impl Handler<UpdateScreen> for DbActor {
     type Result = Result<Screen, AppError>;
 
     fn handle(&mut self, msg: UpdateScreen, _: &mut Self::Context) -> Self::Result {
-        let sub_log = msg.logger.new(o!("handle" => "update_address"));
+        let sub_log = msg.logger.new(o!("handle" => "update_screen"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
+        msg.characteristics.validate()?;
+
+        let screen = find_business_screen(&mut conn, msg.id, msg.business_id)?;
+        if screen.status == ScreenStatus::Archived.to_string() {
+            return Err(AppError::new(
+                Some("Archived screens cannot be changed".to_string()),
+                None,
+                AppErrorType::StatusTransitionError,
+            ));
+        }
+
         let updated_screen = diesel::update(screens)
             .filter(screen_screen_id_column.eq(msg.id))
             .set((
@@ -119,13 +269,103 @@ impl Handler<UpdateScreen> for DbActor {
     }
 }
 
+impl Handler<DeleteScreen> for DbActor {
+    type Result = Result<ScreenDeletion, AppError>;
+
+    fn handle(&mut self, msg: DeleteScreen, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "delete_screen"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        conn.transaction::<_, AppError, _>(|conn| {
+            // Orders take a key share lock on their screen, so none can be booked on it between
+            // the check below and the delete.
+            screens
+                .find(msg.id)
+                .select(screen_screen_id_column)
+                .for_update()
+                .first::<Uuid>(conn)
+                .optional()?;
+            find_business_screen(conn, msg.id, msg.business_id)?;
+
+            if screen_has_upcoming_orders(conn, msg.id)? {
+                return Err(AppError::new(
+                    Some("Screen has upcoming ad orders".to_string()),
+                    None,
+                    AppErrorType::BookingConflictError,
+                ));
+            }
+
+            // Past orders, incomes and payments keep pointing at the screen, so it is only
+            // hidden from clients.
+            let archived = screen_has_orders(conn, msg.id)?;
+            if archived {
+                diesel::update(screens.find(msg.id))
+                    .set(screen_status_column.eq(ScreenStatus::Archived.to_string()))
+                    .execute(conn)?;
+            } else {
+                diesel::delete(screens.find(msg.id)).execute(conn)?;
+            }
+
+            Ok(ScreenDeletion {
+                screen_id: msg.id,
+                archived,
+            })
+        })
+    }
+}
+
+/// Screens whose characteristics match the filter, compared with JSONB operators. Screens that
+/// lack a characteristic the filter asks about don't match.
+fn matching_screens(filter: &ScreenFilter) -> crate::schema::screens::BoxedQuery<'static, Pg> {
+    let mut query = screens.into_boxed();
+
+    if let Some(required) = filter.required_characteristics() {
+        query = query.filter(sql::<Bool>("screens.characteristics @> ").bind::<Jsonb, _>(required));
+    }
+    if let Some(min) = filter.min_size_inches {
+        query = query.filter(
+            sql::<Bool>("(screens.characteristics->>'size_inches')::float8 >= ")
+                .bind::<Double, _>(min),
+        );
+    }
+    if let Some(max) = filter.max_size_inches {
+        query = query.filter(
+            sql::<Bool>("(screens.characteristics->>'size_inches')::float8 <= ")
+                .bind::<Double, _>(max),
+        );
+    }
+    if let Some(min) = filter.min_width {
+        query = query.filter(
+            sql::<Bool>("(screens.characteristics->'resolution'->>'width')::float8 >= ")
+                .bind::<Double, _>(f64::from(min)),
+        );
+    }
+    if let Some(min) = filter.min_height {
+        query = query.filter(
+            sql::<Bool>("(screens.characteristics->'resolution'->>'height')::float8 >= ")
+                .bind::<Double, _>(f64::from(min)),
+        );
+    }
+
+    query
+}
+
 impl Handler<GetAllScreens> for DbActor {
     type Result = Result<Vec<Screen>, AppError>;
 
     fn handle(&mut self, msg: GetAllScreens, _: &mut Self::Context) -> Self::Result {
         let sub_log = msg.logger.new(o!("handle" => "get_all_screens"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
-        let result = screens.get_results::<Screen>(&mut conn)?;
+        let open_at = msg.filter.open_at()?;
+
+        // Operating hours can run past midnight, so they are checked here once the rest of the
+        // filter has been applied in SQL.
+        let result = matching_screens(&msg.filter)
+            .filter(screen_status_column.eq(ScreenStatus::Active.to_string()))
+            .get_results::<Screen>(&mut conn)?
+            .into_iter()
+            .filter(|screen| msg.filter.is_open(&screen.characteristics, open_at))
+            .collect();
         Ok(result)
     }
 }
@@ -173,6 +413,11 @@ impl Handler<GetScreenDataById> for DbActor {
 
             match address_name_query {
                 Ok(address_name) => {
+                    let (online_status, last_seen_at) = find_screen_online_status(
+                        &mut conn,
+                        msg.screen_id,
+                        msg.offline_after_micros,
+                    )?;
                     let screen_data_with_address = ScreenDataWithAddress {
                         screen_id: msg.screen_id,
                         screen_name: screen_data.screen_name,
@@ -181,6 +426,8 @@ impl Handler<GetScreenDataById> for DbActor {
                         traffic: screen_data.traffic,
                         address_name,
                         business_id: screen_data.business_id,
+                        online_status: online_status.to_string(),
+                        last_seen_at,
                     };
                     Ok(Some(screen_data_with_address))
                 }
@@ -193,42 +440,511 @@ impl Handler<GetScreenDataById> for DbActor {
 }
 
 impl Handler<GetOptimalScreens> for DbActor {
-    type Result = Result<Vec<Screen>, AppError>;
+    type Result = Result<OptimalScreenSelection, AppError>;
 
     fn handle(&mut self, msg: GetOptimalScreens, _: &mut Self::Context) -> Self::Result {
         let sub_log = msg.logger.new(o!("handle" => "get_optimal_screens"));
         let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
 
-        let mut screens_data: Vec<Screen> = screens
-            .inner_join(businesses.on(business_id_column.eq(business_id_column)))
+        if msg.end_time <= msg.start_time {
+            return Err(AppError::new(
+                Some("Campaign must end after it starts".to_string()),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+        if !(msg.user_budget.is_finite() && msg.user_budget >= 0.0) {
+            return Err(AppError::new(
+                Some("Budget must be a non-negative amount".to_string()),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+
+        let booked_screen_ids = find_booked_screen_ids(
+            &mut conn,
+            PgTimestamp(msg.start_time),
+            PgTimestamp(msg.end_time),
+            msg.include_pending,
+        )?;
+
+        let mut ad_category_ids = msg.ad_category_ids;
+        ad_category_ids.sort();
+        ad_category_ids.dedup();
+        let category_count = ad_category_ids.len();
+
+        // One row per screen and matching category of its business.
+        let matches: Vec<(Screen, Uuid)> = screens
             .inner_join(
                 business_categories
-                    .on(business_id_column.eq(business_categories_business_id_column)),
+                    .on(screen_business_id_column.eq(business_categories_business_id_column)),
             )
-            .filter(business_categories_cat_id_column.eq_any(msg.ad_category_ids))
-            .select(Screen::as_select())
-            .load::<Screen>(&mut conn)?;
+            .filter(business_categories_cat_id_column.eq_any(ad_category_ids))
+            .filter(screen_status_column.eq(ScreenStatus::Active.to_string()))
+            .filter(screen_screen_id_column.ne_all(booked_screen_ids))
+            .select((Screen::as_select(), business_categories_cat_id_column))
+            .load::<(Screen, Uuid)>(&mut conn)?;
+
+        let mut matched_screens: HashMap<Uuid, (Screen, usize)> = HashMap::new();
+        for (screen, _) in matches {
+            matched_screens
+                .entry(screen.screen_id)
+                .or_insert((screen, 0))
+                .1 += 1;
+        }
 
-        let result = find_optimal_screens(msg.user_budget, &mut screens_data);
+        let billed_units = msg
+            .billing_unit
+            .billed_units(msg.start_time, msg.end_time)?;
+        let mut candidates: Vec<ScreenCandidate> = matched_screens
+            .into_values()
+            .map(|(screen, matched_categories)| {
+                let reach = (screen.traffic as i64).saturating_mul(billed_units);
+                ScreenCandidate {
+                    price: screen.price_per_time * billed_units as f64,
+                    reach,
+                    // Every candidate is divided by the same category count, so weighting the
+                    // reach by the matched count ranks sets exactly like weighting by relevance.
+                    value: reach.saturating_mul(matched_categories as i64),
+                    relevance: matched_categories as f64 / category_count as f64,
+                    screen,
+                }
+            })
+            .collect();
+        // The map iterates in random order, and ties between equally good sets go to the
+        // candidates seen first, so the order is fixed to return the same screens every time.
+        candidates.sort_by_key(|candidate| candidate.screen.screen_id);
+
+        let chosen = pack_screens(msg.user_budget, candidates);
+
+        Ok(OptimalScreenSelection {
+            total_price: chosen.iter().map(|candidate| candidate.price).sum(),
+            expected_reach: chosen.iter().map(|candidate| candidate.reach).sum(),
+            screens: chosen
+                .into_iter()
+                .map(|candidate| OptimalScreen {
+                    screen: candidate.screen,
+                    relevance: candidate.relevance,
+                    expected_reach: candidate.reach,
+                })
+                .collect(),
+        })
+    }
+}
 
-        fn find_optimal_screens(user_budget: f64, screens_data: &mut Vec<Screen>) -> Vec<Screen> {
-            let mut optimal_screens: Vec<Screen> = Vec::new();
-            let mut remaining_budget = user_budget;
+/// Picks the screens with the highest total value that fit in the budget (0/1 knapsack).
+///
+/// Prices are rounded up to whole budget steps, so the chosen set never costs more than the
+/// budget. Among sets with the same value the cheapest one wins.
+fn pack_screens(budget: f64, candidates: Vec<ScreenCandidate>) -> Vec<ScreenCandidate> {
+    let step = (budget / MAX_BUDGET_STEPS).max(MIN_BUDGET_STEP);
+    let capacity = (budget / step).floor() as usize;
+
+    // Screens without value are never picked, so they are dropped before they take up room.
+    let mut candidates: Vec<(usize, ScreenCandidate)> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.price >= 0.0 && candidate.value > 0)
+        .map(|candidate| {
+            (
+                (candidate.price / step - 1e-9).ceil().max(0.0) as usize,
+                candidate,
+            )
+        })
+        .filter(|(weight, _)| *weight <= capacity)
+        .collect();
+
+    if candidates.len() > MAX_OPTIMAL_CANDIDATES {
+        // Free screens divide by zero into infinity, which keeps them first.
+        let density = |(weight, candidate): &(usize, ScreenCandidate)| {
+            candidate.value as f64 / *weight as f64
+        };
+        candidates.sort_by(|a, b| density(b).total_cmp(&density(a)));
+        candidates.truncate(MAX_OPTIMAL_CANDIDATES);
+    }
 
-            screens_data.sort_by_key(|screen| {
-                Reverse((screen.traffic as f64 / screen.price_per_time * 1000.0) as i64)
-            });
+    // best_value[w] is the highest value reachable within w steps; taken records whether
+    // candidate i is part of that best set.
+    let mut best_value = vec![0i64; capacity + 1];
+    let mut taken = BitGrid::new(candidates.len(), capacity + 1);
+    for (i, (weight, candidate)) in candidates.iter().enumerate() {
+        for w in (*weight..=capacity).rev() {
+            let with_candidate = best_value[w - weight] + candidate.value;
+            if with_candidate > best_value[w] {
+                best_value[w] = with_candidate;
+                taken.set(i, w);
+            }
+        }
+    }
+
+    let mut w = best_value
+        .iter()
+        .position(|value| *value == best_value[capacity])
+        .unwrap_or(0);
+    let mut is_chosen = vec![false; candidates.len()];
+    for i in (0..candidates.len()).rev() {
+        if taken.get(i, w) {
+            is_chosen[i] = true;
+            w -= candidates[i].0;
+        }
+    }
+
+    candidates
+        .into_iter()
+        .zip(is_chosen)
+        .filter_map(|((_, candidate), is_chosen)| is_chosen.then_some(candidate))
+        .collect()
+}
+
+/// Rows of bits packed into words, so the knapsack keeps one bit per candidate and budget step.
+struct BitGrid {
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    fn new(rows: usize, columns: usize) -> Self {
+        let words_per_row = columns.div_ceil(64);
+        BitGrid {
+            words_per_row,
+            words: vec![0; rows * words_per_row],
+        }
+    }
+
+    fn set(&mut self, row: usize, column: usize) {
+        self.words[row * self.words_per_row + column / 64] |= 1 << (column % 64);
+    }
+
+    fn get(&self, row: usize, column: usize) -> bool {
+        self.words[row * self.words_per_row + column / 64] & (1 << (column % 64)) != 0
+    }
+}
+
+impl Handler<GetScreenAvailability> for DbActor {
+    type Result = Result<ScreenAvailability, AppError>;
+
+    fn handle(&mut self, msg: GetScreenAvailability, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_screen_availability"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        if msg.to <= msg.from {
+            return Err(AppError::new(
+                Some("Availability window must end after it starts".to_string()),
+                None,
+                AppErrorType::ValidationError,
+            ));
+        }
+
+        let slot_length = match msg.slot_minutes {
+            Some(minutes) if minutes <= 0 => {
+                return Err(AppError::new(
+                    Some("Slot length must be positive".to_string()),
+                    None,
+                    AppErrorType::ValidationError,
+                ));
+            }
+            Some(minutes) => {
+                let slot_length = minutes * BillingUnit::Minute.micros();
+                if (msg.to - msg.from) / slot_length >= MAX_AVAILABILITY_SLOTS {
+                    return Err(AppError::new(
+                        Some(format!(
+                            "Availability window holds more than {} slots",
+                            MAX_AVAILABILITY_SLOTS
+                        )),
+                        None,
+                        AppErrorType::ValidationError,
+                    ));
+                }
+                Some(slot_length)
+            }
+            None => None,
+        };
+
+        let screen_exists: bool =
+            select(exists(screens.find(msg.screen_id))).get_result(&mut conn)?;
+
+        if !screen_exists {
+            return Err(AppError::new(
+                Some("Screen not found".to_string()),
+                None,
+                AppErrorType::NotFoundError,
+            ));
+        }
 
-            for screen in screens_data.iter_mut() {
-                if screen.price_per_time <= remaining_budget {
-                    optimal_screens.push(screen.clone());
-                    remaining_budget -= screen.price_per_time;
+        let booked_times = find_booked_times(
+            &mut conn,
+            msg.screen_id,
+            PgTimestamp(msg.from),
+            PgTimestamp(msg.to),
+            msg.include_pending,
+        )?;
+
+        // Orders come sorted by start time; clamp them to the window and merge overlaps.
+        let mut booked: Vec<AvailabilityInterval> = Vec::new();
+        for (start_time, end_time) in booked_times {
+            let start_time = start_time.0.max(msg.from);
+            let end_time = end_time.0.min(msg.to);
+            match booked.last_mut() {
+                Some(last) if start_time <= last.end_time => {
+                    last.end_time = last.end_time.max(end_time);
                 }
+                _ => booked.push(AvailabilityInterval {
+                    start_time,
+                    end_time,
+                }),
+            }
+        }
+
+        let mut free = Vec::new();
+        let mut cursor = msg.from;
+        for interval in &booked {
+            if interval.start_time > cursor {
+                free.push(AvailabilityInterval {
+                    start_time: cursor,
+                    end_time: interval.start_time,
+                });
+            }
+            cursor = interval.end_time;
+        }
+        if cursor < msg.to {
+            free.push(AvailabilityInterval {
+                start_time: cursor,
+                end_time: msg.to,
+            });
+        }
+
+        let slots = slot_length.map(|slot_length| {
+            let mut slots = Vec::new();
+            let mut start_time = msg.from;
+            while start_time < msg.to {
+                let end_time = (start_time + slot_length).min(msg.to);
+                let is_free = !booked.iter().any(|interval| {
+                    interval.start_time < end_time && interval.end_time > start_time
+                });
+                slots.push(AvailabilitySlot {
+                    start_time,
+                    end_time,
+                    is_free,
+                });
+                start_time = end_time;
             }
+            slots
+        });
+
+        Ok(ScreenAvailability {
+            screen_id: msg.screen_id,
+            from: msg.from,
+            to: msg.to,
+            booked,
+            free,
+            slots,
+        })
+    }
+}
+
+impl Handler<GetNearbyScreens> for DbActor {
+    type Result = Result<Vec<NearbyScreen>, AppError>;
 
-            optimal_screens
+    fn handle(&mut self, msg: GetNearbyScreens, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_nearby_screens"));
+        let mut conn = get_pooled_connection(&self.0, sub_log.clone())?;
+
+        validate_coordinates(msg.latitude, msg.longitude)?;
+        if !(msg.radius_km > 0.0 && msg.radius_km <= MAX_NEARBY_RADIUS_KM) {
+            return Err(AppError::new(
+                Some(format!(
+                    "Radius must be greater than 0 and at most {} km",
+                    MAX_NEARBY_RADIUS_KM
+                )),
+                None,
+                AppErrorType::ValidationError,
+            ));
         }
 
-        Ok(result)
+        // The bounding box narrows the candidates on the coordinates index,
+        // the exact distance is checked below.
+        let bounds = BoundingBox::around(msg.latitude, msg.longitude, msg.radius_km);
+        let (longitude_range, wrapped_longitude_range) = bounds.longitude_ranges();
+
+        let query = screens
+            .inner_join(addresses.on(address_id.eq(screen_address_id)))
+            .filter(screen_status_column.eq(ScreenStatus::Active.to_string()))
+            .filter(address_latitude_column.between(bounds.min_latitude, bounds.max_latitude))
+            .select((Screen::as_select(), Address::as_select()))
+            .into_boxed();
+        let query = match wrapped_longitude_range {
+            Some((min_longitude, max_longitude)) => query.filter(
+                address_longitude_column
+                    .between(longitude_range.0, longitude_range.1)
+                    .or(address_longitude_column.between(min_longitude, max_longitude)),
+            ),
+            None => {
+                query.filter(address_longitude_column.between(longitude_range.0, longitude_range.1))
+            }
+        };
+
+        let candidates = query.load::<(Screen, Address)>(&mut conn)?;
+
+        let mut nearby_screens: Vec<NearbyScreen> = candidates
+            .into_iter()
+            .filter_map(|(screen, address)| {
+                let distance_km = haversine_km(
+                    msg.latitude,
+                    msg.longitude,
+                    address.latitude?,
+                    address.longitude?,
+                );
+                (distance_km <= msg.radius_km).then_some(NearbyScreen {
+                    screen,
+                    address,
+                    distance_km,
+                })
+            })
+            .collect();
+        nearby_screens.sort_by(|a, b| a.distance_km.total_cmp(&b.distance_km));
+
+        Ok(nearby_screens)
+    }
+}
+
+impl Handler<GetScreenResolutions> for DbActor {
+    type Result = Result<Vec<Resolution>, AppError>;
+
+    fn handle(&mut self, msg: GetScreenResolutions, _: &mut Self::Context) -> Self::Result {
+        let sub_log = msg.logger.new(o!("handle" => "get_screen_resolutions"));
+        let mut conn = get_pooled_connection(&self.0, sub_log)?;
+
+        let characteristics: Vec<ScreenCharacteristics> = screens
+            .filter(screen_status_column.eq(ScreenStatus::Active.to_string()))
+            .select(screen_characteristics_column)
+            .get_results(&mut conn)?;
+
+        let mut screen_counts: HashMap<(i32, i32), usize> = HashMap::new();
+        for resolution in characteristics.into_iter().filter_map(|c| c.resolution) {
+            *screen_counts
+                .entry((resolution.width, resolution.height))
+                .or_default() += 1;
+        }
+
+        let mut resolutions: Vec<((i32, i32), usize)> = screen_counts.into_iter().collect();
+        resolutions.sort_by(|(a_size, a_count), (b_size, b_count)| {
+            b_count.cmp(a_count).then(b_size.cmp(a_size))
+        });
+
+        Ok(resolutions
+            .into_iter()
+            .take(msg.limit)
+            .map(|((width, height), _)| Resolution { width, height })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(price: f64, value: i64) -> ScreenCandidate {
+        ScreenCandidate {
+            screen: Screen {
+                screen_id: Uuid::new_v4(),
+                screen_name: format!("{} for {}", value, price),
+                price_per_time: price,
+                characteristics: ScreenCharacteristics {
+                    resolution: None,
+                    orientation: None,
+                    size_inches: None,
+                    placement: None,
+                    media_types: vec![],
+                    operating_hours: None,
+                    description: None,
+                },
+                traffic: 0,
+                business_id: Uuid::new_v4(),
+                address_id: Uuid::new_v4(),
+                status: ScreenStatus::Active.to_string(),
+            },
+            price,
+            reach: value,
+            relevance: 1.0,
+            value,
+        }
+    }
+
+    fn packed(budget: f64, candidates: Vec<ScreenCandidate>) -> Vec<(f64, i64)> {
+        let mut chosen: Vec<(f64, i64)> = pack_screens(budget, candidates)
+            .into_iter()
+            .map(|candidate| (candidate.price, candidate.value))
+            .collect();
+        chosen.sort_by(|a, b| a.0.total_cmp(&b.0));
+        chosen
+    }
+
+    #[test]
+    fn picks_the_most_valuable_set_within_the_budget() {
+        let candidates = vec![
+            candidate(5.0, 10),
+            candidate(4.0, 40),
+            candidate(6.0, 30),
+            candidate(3.0, 50),
+        ];
+
+        assert_eq!(packed(10.0, candidates), vec![(3.0, 50), (4.0, 40)]);
+    }
+
+    #[test]
+    fn rounds_prices_up_so_the_budget_is_never_exceeded() {
+        let candidates = (0..3).map(|_| candidate(0.335, 1)).collect();
+
+        assert_eq!(packed(1.0, candidates).len(), 2);
+    }
+
+    #[test]
+    fn prefers_the_cheaper_of_equally_valuable_sets() {
+        let candidates = vec![candidate(5.0, 10), candidate(3.0, 10)];
+
+        assert_eq!(packed(5.0, candidates), vec![(3.0, 10)]);
+    }
+
+    #[test]
+    fn keeps_free_screens_with_no_budget() {
+        let candidates = vec![candidate(0.0, 7), candidate(0.01, 100), candidate(0.0, 0)];
+
+        assert_eq!(packed(0.0, candidates), vec![(0.0, 7)]);
+    }
+
+    #[test]
+    fn skips_screens_that_do_not_fit_or_have_invalid_prices() {
+        let candidates = vec![
+            candidate(11.0, 100),
+            candidate(-1.0, 100),
+            candidate(2.0, 1),
+        ];
+
+        assert_eq!(packed(10.0, candidates), vec![(2.0, 1)]);
+    }
+
+    #[test]
+    fn keeps_the_densest_screens_when_too_many_match() {
+        let mut candidates: Vec<ScreenCandidate> = (0..MAX_OPTIMAL_CANDIDATES)
+            .map(|_| candidate(1.0, 1))
+            .collect();
+        candidates.push(candidate(1.0, 100));
+
+        let chosen = packed(5.0, candidates);
+
+        assert_eq!(chosen.len(), 5);
+        assert!(chosen.contains(&(1.0, 100)));
+    }
+
+    #[test]
+    fn bit_grid_keeps_rows_apart() {
+        let mut grid = BitGrid::new(2, 130);
+        grid.set(0, 129);
+        grid.set(1, 0);
+
+        assert!(grid.get(0, 129));
+        assert!(grid.get(1, 0));
+        assert!(!grid.get(0, 0));
+        assert!(!grid.get(1, 129));
+        assert!(!grid.get(0, 64));
     }
 }
@@ -1,10 +1,16 @@
 use crate::actors::db::DbActor;
 use crate::db_utils::get_pool;
+use crate::errors::{AppError, AppErrorType};
+use crate::media_store::fs::FsMediaStore;
+use crate::media_store::s3::S3MediaStore;
+use crate::media_store::MediaStore;
+use crate::payment_provider::mock::MockPaymentProvider;
+use crate::payment_provider::PaymentProvider;
 use actix::{Addr, SyncArbiter};
 use serde::Deserialize;
 use slog::{o, Drain, Logger};
-use slog_async;
-use slog_term;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Deserialize)]
 pub struct ServerConfig {
@@ -12,9 +18,97 @@ pub struct ServerConfig {
     pub port: i32,
 }
 
+/// Length of time that a screen's `price_per_time` is charged for.
+#[derive(Clone, Copy, Debug)]
+pub enum BillingUnit {
+    Minute,
+    Hour,
+    /// Fixed slot of the given number of minutes.
+    Slot(i64),
+}
+
+impl BillingUnit {
+    const MICROS_PER_MINUTE: i64 = 60_000_000;
+
+    pub fn micros(&self) -> i64 {
+        match self {
+            BillingUnit::Minute => Self::MICROS_PER_MINUTE,
+            BillingUnit::Hour => 60 * Self::MICROS_PER_MINUTE,
+            BillingUnit::Slot(minutes) => minutes * Self::MICROS_PER_MINUTE,
+        }
+    }
+
+    /// Number of units charged for a booking, counting every started unit.
+    pub fn billed_units(&self, start_time: i64, end_time: i64) -> Result<i64, AppError> {
+        let unit = self.micros();
+        end_time
+            .checked_sub(start_time)
+            .and_then(|length| length.checked_add(unit - 1))
+            .map(|length| length / unit)
+            .ok_or_else(|| {
+                AppError::new(
+                    Some("Booking is too long to be priced".to_string()),
+                    None,
+                    AppErrorType::ValidationError,
+                )
+            })
+    }
+}
+
+/// Rules applied when clients book screen time.
+#[derive(Clone, Copy)]
+pub struct BookingConfig {
+    /// Whether orders still waiting for approval already hold their screen time.
+    pub blocks_pending: bool,
+    pub billing_unit: BillingUnit,
+    /// How many hours before its start an order can still be cancelled by the client.
+    pub cancellation_window_hours: i64,
+}
+
+/// Rules applied when businesses manage their screens.
+#[derive(Clone, Copy)]
+pub struct ScreenConfig {
+    /// Whether screens created by businesses wait for an admin before they can be booked.
+    pub approval_required: bool,
+    /// How long a screen can go without a heartbeat before it is considered offline.
+    pub offline_after_minutes: i64,
+}
+
+impl ScreenConfig {
+    pub fn offline_after_micros(&self) -> i64 {
+        self.offline_after_minutes * 60_000_000
+    }
+}
+
+/// Removal of stored media that no user, business or ad refers to anymore.
+#[derive(Clone, Copy)]
+pub struct MediaGcConfig {
+    pub interval_minutes: u64,
+    /// How old unreferenced media must be before it is deleted, so that fresh uploads have time
+    /// to be attached to an ad.
+    pub grace_hours: u64,
+    /// Only report orphaned media instead of deleting it.
+    pub dry_run: bool,
+}
+
+impl MediaGcConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_minutes * 60)
+    }
+
+    pub fn grace_period(&self) -> Duration {
+        Duration::from_secs(self.grace_hours * 60 * 60)
+    }
+}
+
 pub struct Config {
     pub server: ServerConfig,
+    pub booking: BookingConfig,
+    pub screens: ScreenConfig,
     pub db: Addr<DbActor>,
+    pub payment_provider: Arc<dyn PaymentProvider>,
+    pub media_store: Arc<dyn MediaStore>,
+    pub media_gc: MediaGcConfig,
 }
 
 impl Config {
@@ -24,12 +118,116 @@ impl Config {
         let pool = get_pool(&db_url);
         let db_addr = SyncArbiter::start(3, move || DbActor(pool.clone()));
 
+        let payment_provider: Arc<dyn PaymentProvider> = match dotenv::var("PAYMENT_PROVIDER")
+            .as_deref()
+        {
+            Ok("mock") | Err(_) => Arc::new(MockPaymentProvider::new(
+                dotenv::var("PAYMENT_WEBHOOK_SECRET").expect("PAYMENT_WEBHOOK_SECRET must be set!"),
+            )),
+            Ok(provider) => panic!("Unsupported payment provider: {}", provider),
+        };
+
+        let media_store: Arc<dyn MediaStore> = match dotenv::var("MEDIA_STORE").as_deref() {
+            Ok("fs") | Err(_) => Arc::new(FsMediaStore::new(
+                dotenv::var("MEDIA_DIR")
+                    .unwrap_or_else(|_| "media".to_string())
+                    .into(),
+            )),
+            Ok("s3") => Arc::new(S3MediaStore::new(
+                dotenv::var("S3_ENDPOINT").expect("S3_ENDPOINT must be set!"),
+                dotenv::var("S3_BUCKET").expect("S3_BUCKET must be set!"),
+                dotenv::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                dotenv::var("S3_ACCESS_KEY_ID").expect("S3_ACCESS_KEY_ID must be set!"),
+                dotenv::var("S3_SECRET_ACCESS_KEY").expect("S3_SECRET_ACCESS_KEY must be set!"),
+                dotenv::var("S3_PRESIGN_EXPIRY_SECONDS")
+                    .map(|seconds| {
+                        seconds
+                            .parse::<u64>()
+                            .expect("S3_PRESIGN_EXPIRY_SECONDS must be a number")
+                    })
+                    .unwrap_or(900),
+            )),
+            Ok(store) => panic!("Unsupported media store: {}", store),
+        };
+
+        let booking = BookingConfig {
+            blocks_pending: dotenv::var("BOOKING_BLOCKS_PENDING")
+                .map(|value| value == "true")
+                .unwrap_or(false),
+            billing_unit: match dotenv::var("BILLING_UNIT").as_deref() {
+                Ok("minute") => BillingUnit::Minute,
+                Ok("hour") | Err(_) => BillingUnit::Hour,
+                Ok("slot") => {
+                    let minutes = dotenv::var("BILLING_SLOT_MINUTES")
+                        .expect("BILLING_SLOT_MINUTES must be set!")
+                        .parse::<i64>()
+                        .expect("BILLING_SLOT_MINUTES must be a number");
+                    assert!(minutes > 0, "BILLING_SLOT_MINUTES must be positive");
+                    BillingUnit::Slot(minutes)
+                }
+                Ok(unit) => panic!("Unsupported billing unit: {}", unit),
+            },
+            cancellation_window_hours: dotenv::var("CANCELLATION_WINDOW_HOURS")
+                .map(|hours| {
+                    hours
+                        .parse::<i64>()
+                        .expect("CANCELLATION_WINDOW_HOURS must be a number")
+                })
+                .unwrap_or(24),
+        };
+
+        let screens = ScreenConfig {
+            approval_required: dotenv::var("SCREEN_APPROVAL_REQUIRED")
+                .map(|value| value == "true")
+                .unwrap_or(false),
+            offline_after_minutes: dotenv::var("SCREEN_OFFLINE_AFTER_MINUTES")
+                .map(|minutes| {
+                    minutes
+                        .parse::<i64>()
+                        .expect("SCREEN_OFFLINE_AFTER_MINUTES must be a number")
+                })
+                .unwrap_or(10),
+        };
+        assert!(
+            screens.offline_after_minutes > 0,
+            "SCREEN_OFFLINE_AFTER_MINUTES must be positive"
+        );
+
+        let media_gc = MediaGcConfig {
+            interval_minutes: dotenv::var("MEDIA_GC_INTERVAL_MINUTES")
+                .map(|minutes| {
+                    minutes
+                        .parse::<u64>()
+                        .expect("MEDIA_GC_INTERVAL_MINUTES must be a number")
+                })
+                .unwrap_or(60),
+            grace_hours: dotenv::var("MEDIA_GC_GRACE_HOURS")
+                .map(|hours| {
+                    hours
+                        .parse::<u64>()
+                        .expect("MEDIA_GC_GRACE_HOURS must be a number")
+                })
+                .unwrap_or(24),
+            dry_run: dotenv::var("MEDIA_GC_DRY_RUN")
+                .map(|value| value == "true")
+                .unwrap_or(false),
+        };
+        assert!(
+            media_gc.interval_minutes > 0,
+            "MEDIA_GC_INTERVAL_MINUTES must be positive"
+        );
+
         Self {
             server: ServerConfig {
                 host: "localhost".parse().unwrap(),
                 port: 4000,
             },
+            booking,
+            screens,
             db: db_addr,
+            payment_provider,
+            media_store,
+            media_gc,
         }
     }
 
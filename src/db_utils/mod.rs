@@ -1,7 +1,12 @@
 use diesel::{
+    data_types::PgTimestamp,
     r2d2::{ConnectionManager, Pool},
     PgConnection,
 };
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Postgres counts timestamps in microseconds from 2000-01-01 UTC.
+pub const PG_EPOCH_UNIX_MICROS: i64 = 946_684_800_000_000;
 
 /*
 pub fn run_migrations(db_url: &str) {
@@ -18,3 +23,14 @@ pub fn get_pool(db_url: &str) -> Pool<ConnectionManager<PgConnection>> {
         .build(manager)
         .expect("Error building a connection pool")
 }
+
+pub fn current_pg_timestamp() -> PgTimestamp {
+    pg_timestamp(SystemTime::now())
+}
+
+pub fn pg_timestamp(time: SystemTime) -> PgTimestamp {
+    let since_unix_epoch = time
+        .duration_since(UNIX_EPOCH)
+        .expect("System clock is before the unix epoch");
+    PgTimestamp(since_unix_epoch.as_micros() as i64 - PG_EPOCH_UNIX_MICROS)
+}
@@ -5,12 +5,22 @@ use diesel::r2d2::{Error, PoolError};
 use serde::Serialize;
 use std::fmt;
 
+/// Exclusion constraint that keeps approved orders on one screen from overlapping.
+const AD_ORDERS_OVERLAP_CONSTRAINT: &str = "ad_orders_no_overlap";
+
 #[derive(Debug)]
 pub enum AppErrorType {
     DbError,
     UnverifiedAdError,
     RejectedAdError,
+    PaymentError,
+    ValidationError,
+    BookingConflictError,
+    StatusTransitionError,
+    ForbiddenError,
     NotFoundError,
+    UnsupportedMediaError,
+    MediaTooLargeError,
     SomethingWentWrong,
     PasswordOrLoginError,
     AuthorizeError,
@@ -41,7 +51,7 @@ impl AppError {
     }
 
     pub fn message(&self) -> String {
-        match &*self {
+        match self {
             AppError {
                 message: Some(message),
                 ..
@@ -78,6 +88,16 @@ impl From<Error> for AppError {
 
 impl From<diesel::result::Error> for AppError {
     fn from(error: diesel::result::Error) -> AppError {
+        if let diesel::result::Error::DatabaseError(_, info) = &error {
+            if info.constraint_name() == Some(AD_ORDERS_OVERLAP_CONSTRAINT) {
+                return AppError {
+                    message: Some("Screen is already booked for this time".to_string()),
+                    cause: Some(error.to_string()),
+                    error_type: AppErrorType::BookingConflictError,
+                };
+            }
+        }
+
         AppError {
             message: None,
             cause: Some(error.to_string()),
@@ -124,9 +144,17 @@ impl ResponseError for AppError {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
             AppErrorType::NotFoundError => StatusCode::NOT_FOUND,
+            AppErrorType::ForbiddenError => StatusCode::FORBIDDEN,
+            AppErrorType::UnsupportedMediaError => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppErrorType::MediaTooLargeError => StatusCode::PAYLOAD_TOO_LARGE,
             AppErrorType::PasswordOrLoginError
             | AppErrorType::UnverifiedAdError
-            | AppErrorType::RejectedAdError => StatusCode::BAD_REQUEST,
+            | AppErrorType::RejectedAdError
+            | AppErrorType::PaymentError
+            | AppErrorType::ValidationError => StatusCode::BAD_REQUEST,
+            AppErrorType::BookingConflictError | AppErrorType::StatusTransitionError => {
+                StatusCode::CONFLICT
+            }
             AuthorizeError => StatusCode::INTERNAL_SERVER_ERROR,
             IoError => StatusCode::INTERNAL_SERVER_ERROR,
         }
@@ -0,0 +1,90 @@
+use crate::errors::{AppError, AppErrorType};
+
+/// Mean Earth radius used by the haversine formula.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+/// Length of one degree of latitude on the same sphere, so the bounding box never cuts off
+/// points that `haversine_km` puts within the radius.
+const KM_PER_DEGREE: f64 = EARTH_RADIUS_KM * std::f64::consts::PI / 180.0;
+
+pub fn validate_coordinates(latitude: f64, longitude: f64) -> Result<(), AppError> {
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(AppError::new(
+            Some("Latitude must be within ±90 and longitude within ±180".to_string()),
+            None,
+            AppErrorType::ValidationError,
+        ));
+    }
+    Ok(())
+}
+
+/// Great-circle distance between two points in kilometres.
+pub fn haversine_km(
+    latitude: f64,
+    longitude: f64,
+    other_latitude: f64,
+    other_longitude: f64,
+) -> f64 {
+    let d_lat = (other_latitude - latitude).to_radians();
+    let d_lng = (other_longitude - longitude).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + latitude.to_radians().cos()
+            * other_latitude.to_radians().cos()
+            * (d_lng / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Latitude/longitude rectangle that contains every point within a radius of the centre.
+/// It is only a prefilter, distances still have to be checked with `haversine_km`.
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    pub min_longitude: f64,
+    pub max_longitude: f64,
+}
+
+impl BoundingBox {
+    pub fn around(latitude: f64, longitude: f64, radius_km: f64) -> BoundingBox {
+        let d_lat = radius_km / KM_PER_DEGREE;
+        let min_latitude = (latitude - d_lat).max(-90.0);
+        let max_latitude = (latitude + d_lat).min(90.0);
+
+        // Degrees of longitude shrink towards the poles; near them any longitude can be in range.
+        let widest_latitude = min_latitude.abs().max(max_latitude.abs());
+        let km_per_lng_degree = KM_PER_DEGREE * widest_latitude.to_radians().cos();
+        let d_lng = if km_per_lng_degree > 0.0 {
+            radius_km / km_per_lng_degree
+        } else {
+            180.0
+        };
+
+        let (min_longitude, max_longitude) = if d_lng >= 180.0 {
+            (-180.0, 180.0)
+        } else {
+            (longitude - d_lng, longitude + d_lng)
+        };
+
+        BoundingBox {
+            min_latitude,
+            max_latitude,
+            min_longitude,
+            max_longitude,
+        }
+    }
+
+    /// Longitude range to query, plus a second one when the box crosses the antimeridian.
+    pub fn longitude_ranges(&self) -> ((f64, f64), Option<(f64, f64)>) {
+        if self.min_longitude < -180.0 {
+            (
+                (-180.0, self.max_longitude),
+                Some((self.min_longitude + 360.0, 180.0)),
+            )
+        } else if self.max_longitude > 180.0 {
+            (
+                (self.min_longitude, 180.0),
+                Some((-180.0, self.max_longitude - 360.0)),
+            )
+        } else {
+            ((self.min_longitude, self.max_longitude), None)
+        }
+    }
+}
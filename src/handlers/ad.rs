@@ -1,9 +1,12 @@
 use crate::actors::ad::{CreateAd, GetAllAds, GetUserAds, UpdateAd};
 use crate::errors::AppError;
-use crate::handlers::log_error;
+use crate::handlers::images::{ad_rendition_sizes, check_stored_media, save_media};
+use crate::handlers::{check_admin_override, log_error};
 use crate::middleware::token::TokenClaims;
 use crate::models::ad::{AdData, AdDataUpdate};
 use crate::models::app_state::AppState;
+use crate::models::screen_characteristics::MediaType;
+use actix_multipart::Multipart;
 use actix_web::web::{Data, Json, ReqData};
 use actix_web::{get, post, HttpResponse, Responder};
 use slog::o;
@@ -18,6 +21,7 @@ pub async fn create(
         Some(user) => {
             let db = state.as_ref().db.clone();
             let ad_data = ad_data.into_inner();
+            check_stored_media(state.media_store.clone(), &ad_data.img_url).await?;
 
             let result = match db
                 .send(CreateAd {
@@ -45,29 +49,38 @@ pub async fn create(
 #[post("/update")]
 pub async fn update(
     ad: Json<AdDataUpdate>,
+    req: Option<ReqData<TokenClaims>>,
     state: Data<AppState>,
 ) -> Result<impl Responder, AppError> {
-    let db = state.as_ref().db.clone();
-    let ad = ad.into_inner();
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let ad = ad.into_inner();
+            check_admin_override(ad.admin_override, &user)?;
+            check_stored_media(state.media_store.clone(), &ad.img_url).await?;
 
-    let result = match db
-        .send(UpdateAd {
-            id: ad.ad_id,
-            ad_name: ad.ad_name,
-            img_url: ad.img_url,
-            user_id: ad.user_id,
-            logger: state.logger.clone(),
-        })
-        .await
-    {
-        Ok(res) => res,
-        Err(err) => return Err(AppError::from_mailbox(err)),
-    };
+            let result = match db
+                .send(UpdateAd {
+                    id: ad.ad_id,
+                    ad_name: ad.ad_name,
+                    img_url: ad.img_url,
+                    user_id: user.id,
+                    admin_override: ad.admin_override,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
 
-    let sub_log = state.logger.new(o!("handle" => "update_category"));
-    result
-        .map(|category| HttpResponse::Ok().json(category))
-        .map_err(log_error(sub_log))
+            let sub_log = state.logger.new(o!("handle" => "update_category"));
+            result
+                .map(|category| HttpResponse::Ok().json(category))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
 }
 
 #[get("/get_all")]
@@ -92,10 +105,13 @@ pub async fn get_user_ads(
     match req {
         Some(user) => {
             let db = state.as_ref().db.clone();
-            let result = match db.send(GetUserAds {
-                user_id: user.id,
-                logger: state.logger.clone(),
-            }).await {
+            let result = match db
+                .send(GetUserAds {
+                    user_id: user.id,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
                 Ok(res) => res,
                 Err(err) => return Err(AppError::from_mailbox(err)),
             };
@@ -108,3 +124,28 @@ pub async fn get_user_ads(
         _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
     }
 }
+
+/// Stores an image or video for an ad. The returned `media_id` is what goes into `img_url` when
+/// the ad is created or updated. Images also get renditions sized for dashboards and for the
+/// resolutions of the screens ads play on.
+#[post("/upload_media")]
+pub async fn upload_media(
+    payload: Multipart,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(_) => {
+            let sizes = ad_rendition_sizes(&state).await?;
+            let uploaded_media = save_media(
+                payload,
+                &[MediaType::Image, MediaType::Video],
+                sizes,
+                &state,
+            )
+            .await?;
+            Ok(HttpResponse::Ok().json(uploaded_media))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
@@ -1,12 +1,19 @@
-use crate::actors::ad_order::{ApproveAdOrder, CreateAdOrder, GetBusinessAdOrders, RejectAdOrder};
-use crate::errors::AppError;
-use crate::handlers::log_error;
+use crate::actors::ad_order::{
+    ApproveAdOrder, CancelAdOrder, CreateAdOrder, CreateRecurringAdOrder, GetAdOrderQuote,
+    GetBusinessAdOrders, GetUserAdOrders, PreviewRecurringAdOrder, RejectAdOrder,
+};
+use crate::errors::{AppError, AppErrorType};
+use crate::handlers::{check_admin_override, log_error};
 use crate::middleware::token::TokenClaims;
-use crate::models::ad_order::{AdOrderData, AdOrderId};
+use crate::models::ad_order::{
+    AdOrderData, AdOrderDecision, AdOrderFilter, AdOrderId, AdOrderQuoteData, AdOrderStatus,
+    RecurrencePreviewData, RecurringAdOrderData,
+};
 use crate::models::app_state::AppState;
-use actix_web::web::{Data, Json, ReqData};
+use actix_web::web::{Data, Json, Query, ReqData};
 use actix_web::{get, post, HttpResponse, Responder};
 use slog::o;
+use std::str::FromStr;
 
 #[get("/get_business_ad_orders")]
 pub async fn get_business_ad_orders(
@@ -37,21 +44,153 @@ pub async fn get_business_ad_orders(
     }
 }
 
+#[get("/ad_orders")]
+pub async fn get_user_ad_orders(
+    filter: Query<AdOrderFilter>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let filter = filter.into_inner();
+
+            let statuses = match filter.status {
+                Some(statuses) => Some(
+                    statuses
+                        .split(',')
+                        .map(|status| AdOrderStatus::from_str(status.trim()))
+                        .collect::<Result<Vec<AdOrderStatus>, String>>()
+                        .map_err(|err| {
+                            AppError::new(Some(err), None, AppErrorType::ValidationError)
+                        })?,
+                ),
+                None => None,
+            };
+
+            let result = match db
+                .send(GetUserAdOrders {
+                    user_id: user.id,
+                    statuses,
+                    from: filter.from,
+                    to: filter.to,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "get_user_ad_orders"));
+            result
+                .map(|ad_orders| HttpResponse::Ok().json(ad_orders))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
 #[post("/create_ad_order")]
 pub async fn create_ad_order(
     ad_order_data: Json<AdOrderData>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let ad_order_data = ad_order_data.into_inner();
+            check_admin_override(ad_order_data.admin_override, &user)?;
+
+            let result = match db
+                .send(CreateAdOrder {
+                    start_time: ad_order_data.start_time,
+                    end_time: ad_order_data.end_time,
+                    price: ad_order_data.price,
+                    ad_id: ad_order_data.ad_id,
+                    screen_id: ad_order_data.screen_id,
+                    user_id: user.id,
+                    admin_override: ad_order_data.admin_override,
+                    include_pending: state.booking.blocks_pending,
+                    billing_unit: state.booking.billing_unit,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "create_ad_order"));
+            result
+                .map(|order_id| HttpResponse::Ok().json(AdOrderId { order_id }))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[post("/create_recurring_ad_order")]
+pub async fn create_recurring_ad_order(
+    recurring_data: Json<RecurringAdOrderData>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let recurring_data = recurring_data.into_inner();
+            check_admin_override(recurring_data.admin_override, &user)?;
+
+            let result = match db
+                .send(CreateRecurringAdOrder {
+                    start_time: recurring_data.start_time,
+                    end_time: recurring_data.end_time,
+                    recurrence: recurring_data.recurrence,
+                    price: recurring_data.price,
+                    ad_id: recurring_data.ad_id,
+                    screen_id: recurring_data.screen_id,
+                    user_id: user.id,
+                    admin_override: recurring_data.admin_override,
+                    skip_unavailable: recurring_data.skip_unavailable,
+                    include_pending: state.booking.blocks_pending,
+                    billing_unit: state.booking.billing_unit,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state
+                .logger
+                .new(o!("handle" => "create_recurring_ad_order"));
+            result
+                .map(|recurring_orders| HttpResponse::Ok().json(recurring_orders))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[post("/preview_recurring_ad_order")]
+pub async fn preview_recurring_ad_order(
+    preview_data: Json<RecurrencePreviewData>,
     state: Data<AppState>,
 ) -> Result<impl Responder, AppError> {
     let db = state.as_ref().db.clone();
-    let ad_order_data = ad_order_data.into_inner();
+    let preview_data = preview_data.into_inner();
 
     let result = match db
-        .send(CreateAdOrder {
-            start_time: ad_order_data.start_time,
-            end_time: ad_order_data.end_time,
-            price: ad_order_data.price,
-            ad_id: ad_order_data.ad_id,
-            screen_id: ad_order_data.screen_id,
+        .send(PreviewRecurringAdOrder {
+            start_time: preview_data.start_time,
+            end_time: preview_data.end_time,
+            recurrence: preview_data.recurrence,
+            screen_id: preview_data.screen_id,
+            include_pending: state.booking.blocks_pending,
+            billing_unit: state.booking.billing_unit,
             logger: state.logger.clone(),
         })
         .await
@@ -60,23 +199,28 @@ pub async fn create_ad_order(
         Err(err) => return Err(AppError::from_mailbox(err)),
     };
 
-    let sub_log = state.logger.new(o!("handle" => "create_ad_order"));
+    let sub_log = state
+        .logger
+        .new(o!("handle" => "preview_recurring_ad_order"));
     result
-        .map(|ad_order| HttpResponse::Ok().json(ad_order))
+        .map(|preview| HttpResponse::Ok().json(preview))
         .map_err(log_error(sub_log))
 }
 
-#[post("/reject_ad_order")]
-pub async fn reject_ad_order(
-    ad_order_id: Json<AdOrderId>,
+#[post("/quote_ad_order")]
+pub async fn quote_ad_order(
+    quote_data: Json<AdOrderQuoteData>,
     state: Data<AppState>,
 ) -> Result<impl Responder, AppError> {
     let db = state.as_ref().db.clone();
-    let ad_order_id = ad_order_id.into_inner().order_id;
+    let quote_data = quote_data.into_inner();
 
     let result = match db
-        .send(RejectAdOrder {
-            ad_order_id,
+        .send(GetAdOrderQuote {
+            start_time: quote_data.start_time,
+            end_time: quote_data.end_time,
+            screen_id: quote_data.screen_id,
+            billing_unit: state.booking.billing_unit,
             logger: state.logger.clone(),
         })
         .await
@@ -85,33 +229,110 @@ pub async fn reject_ad_order(
         Err(err) => return Err(AppError::from_mailbox(err)),
     };
 
-    let sub_log = state.logger.new(o!("handle" => "reject_ad_order"));
+    let sub_log = state.logger.new(o!("handle" => "quote_ad_order"));
     result
-        .map(|ad_order| HttpResponse::Ok().json(ad_order))
+        .map(|quote| HttpResponse::Ok().json(quote))
         .map_err(log_error(sub_log))
 }
 
+#[post("/reject_ad_order")]
+pub async fn reject_ad_order(
+    decision: Json<AdOrderDecision>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(business) => {
+            let db = state.as_ref().db.clone();
+            let decision = decision.into_inner();
+            check_admin_override(decision.admin_override, &business)?;
+
+            let result = match db
+                .send(RejectAdOrder {
+                    ad_order_id: decision.order_id,
+                    business_id: business.id,
+                    admin_override: decision.admin_override,
+                    payment_provider: state.payment_provider.clone(),
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "reject_ad_order"));
+            result
+                .map(|ad_order| HttpResponse::Ok().json(ad_order))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
 #[post("/approve_ad_order")]
 pub async fn approve_ad_order(
-    ad_order_id: Json<AdOrderId>,
+    decision: Json<AdOrderDecision>,
+    req: Option<ReqData<TokenClaims>>,
     state: Data<AppState>,
 ) -> Result<impl Responder, AppError> {
-    let db = state.as_ref().db.clone();
-    let ad_order_id = ad_order_id.into_inner().order_id;
+    match req {
+        Some(business) => {
+            let db = state.as_ref().db.clone();
+            let decision = decision.into_inner();
+            check_admin_override(decision.admin_override, &business)?;
 
-    let result = match db
-        .send(ApproveAdOrder {
-            ad_order_id,
-            logger: state.logger.clone(),
-        })
-        .await
-    {
-        Ok(res) => res,
-        Err(err) => return Err(AppError::from_mailbox(err)),
-    };
+            let result = match db
+                .send(ApproveAdOrder {
+                    ad_order_id: decision.order_id,
+                    business_id: business.id,
+                    admin_override: decision.admin_override,
+                    payment_provider: state.payment_provider.clone(),
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
 
-    let sub_log = state.logger.new(o!("handle" => "approve_ad_order"));
-    result
-        .map(|ad_order| HttpResponse::Ok().json(ad_order))
-        .map_err(log_error(sub_log))
+            let sub_log = state.logger.new(o!("handle" => "approve_ad_order"));
+            result
+                .map(|ad_order| HttpResponse::Ok().json(ad_order))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[post("/cancel_ad_order")]
+pub async fn cancel_ad_order(
+    ad_order_id: Json<AdOrderId>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(CancelAdOrder {
+                    ad_order_id: ad_order_id.into_inner().order_id,
+                    user_id: user.id,
+                    cancellation_window_hours: state.booking.cancellation_window_hours,
+                    payment_provider: state.payment_provider.clone(),
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "cancel_ad_order"));
+            result
+                .map(|ad_order| HttpResponse::Ok().json(ad_order))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
 }
@@ -1,14 +1,17 @@
 use crate::actors::address::CreateAddress;
-use crate::actors::admin::{AuthorizeAdmin, ChangeAdStatus, CreateAdmin};
+use crate::actors::admin::{AuthorizeAdmin, ChangeAdStatus, ChangeScreenStatus, CreateAdmin};
+use crate::actors::audit_log::{GetAuditLogs, DEFAULT_AUDIT_LOG_PAGE_SIZE};
 use crate::actors::screens::CreateScreen;
 use crate::errors::AppError;
 use crate::handlers::log_error;
+use crate::media_store::gc::collect_media_garbage;
 use crate::models::ad::AdStatusUpdate;
 use crate::models::address::AddressData;
 use crate::models::app_state::AppState;
-use crate::models::screen::ScreenData;
+use crate::models::audit_log::AuditLogQuery;
+use crate::models::screen::{ScreenData, ScreenStatus, ScreenStatusUpdate};
 use crate::models::user::UserData;
-use actix_web::web::{Data, Json};
+use actix_web::web::{Data, Json, Query};
 use actix_web::{get, post, HttpResponse, Responder};
 use actix_web_httpauth::extractors::basic::BasicAuth;
 use slog::o;
@@ -72,6 +75,10 @@ pub async fn create_address(
         .send(CreateAddress {
             name: address_data.address_name,
             business_id: address_data.business_id,
+            latitude: address_data.latitude,
+            longitude: address_data.longitude,
+            city: address_data.city,
+            region: address_data.region,
             logger: state.logger.clone(),
         })
         .await
@@ -102,6 +109,7 @@ pub async fn create_screen(
             traffic: screen_data.traffic,
             business_id: screen_data.business_id,
             address_id: screen_data.address_id,
+            status: ScreenStatus::Active,
             logger: state.logger.clone(),
         })
         .await
@@ -141,3 +149,72 @@ pub async fn change_ad_status(
         .map(|status| HttpResponse::Ok().json(status))
         .map_err(log_error(sub_log))
 }
+
+#[post("/change_screen_status")]
+pub async fn change_screen_status(
+    screen_data: Json<ScreenStatusUpdate>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let db = state.as_ref().db.clone();
+    let screen_data = screen_data.into_inner();
+
+    let result = match db
+        .send(ChangeScreenStatus {
+            screen_id: screen_data.screen_id,
+            new_status: screen_data.new_status,
+            logger: state.logger.clone(),
+        })
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => return Err(AppError::from_mailbox(err)),
+    };
+
+    let sub_log = state.logger.new(o!("handle" => "change_screen_status"));
+    result
+        .map(|screen| HttpResponse::Ok().json(screen))
+        .map_err(log_error(sub_log))
+}
+
+#[get("/audit_logs")]
+pub async fn get_audit_logs(
+    audit_log_query: Query<AuditLogQuery>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let db = state.as_ref().db.clone();
+    let audit_log_query = audit_log_query.into_inner();
+
+    let result = match db
+        .send(GetAuditLogs {
+            limit: audit_log_query.limit.unwrap_or(DEFAULT_AUDIT_LOG_PAGE_SIZE),
+            offset: audit_log_query.offset.unwrap_or(0),
+            logger: state.logger.clone(),
+        })
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => return Err(AppError::from_mailbox(err)),
+    };
+
+    let sub_log = state.logger.new(o!("handle" => "get_audit_logs"));
+    result
+        .map(|audit_logs| HttpResponse::Ok().json(audit_logs))
+        .map_err(log_error(sub_log))
+}
+
+/// Dry run of the media garbage collection: lists the stored media that would be deleted,
+/// without deleting anything.
+#[get("/media_gc_report")]
+pub async fn get_media_gc_report(state: Data<AppState>) -> Result<impl Responder, AppError> {
+    let sub_log = state.logger.new(o!("handle" => "get_media_gc_report"));
+    collect_media_garbage(
+        &state.db,
+        state.media_store.clone(),
+        state.media_gc,
+        true,
+        &sub_log,
+    )
+    .await
+    .map(|report| HttpResponse::Ok().json(report))
+    .map_err(log_error(sub_log))
+}
@@ -8,6 +8,7 @@ use crate::handlers::log_error;
 use crate::middleware::token::TokenClaims;
 use crate::models::app_state::AppState;
 use crate::models::business::{BusinessData, BusinessInfo};
+use crate::models::media::ImageInfo;
 use actix_multipart::Multipart;
 use actix_web::web::{Data, Json, ReqData};
 use actix_web::{get, post, HttpResponse, Responder};
@@ -143,11 +144,11 @@ pub async fn change_img(
 ) -> Result<impl Responder, AppError> {
     match req {
         Some(business) => {
-            let img_url = save_files(payload).await?;
+            let uploaded_media = save_files(payload, &state).await?;
 
             let change_img = ChangeImg {
                 business_id: business.id,
-                img_url,
+                img_url: uploaded_media.media_id,
             };
 
             let db = state.as_ref().db.clone();
@@ -159,7 +160,12 @@ pub async fn change_img(
             let sub_log = state.logger.new(o!("handle" => "change img for business"));
 
             result
-                .map(|img_url| HttpResponse::Ok().json(img_url))
+                .map(|img_url| {
+                    HttpResponse::Ok().json(ImageInfo {
+                        img_url,
+                        img_renditions: uploaded_media.renditions,
+                    })
+                })
                 .map_err(log_error(sub_log))
         }
         _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
@@ -0,0 +1,107 @@
+use crate::actors::campaign::{CreateCampaign, GetCampaign, GetUserCampaigns};
+use crate::errors::AppError;
+use crate::handlers::log_error;
+use crate::middleware::token::TokenClaims;
+use crate::models::app_state::AppState;
+use crate::models::campaign::CampaignData;
+use actix_web::web::{Data, Json, Path, ReqData};
+use actix_web::{get, post, HttpResponse, Responder};
+use slog::o;
+use uuid::Uuid;
+
+#[post("/create")]
+pub async fn create(
+    campaign_data: Json<CampaignData>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let campaign_data = campaign_data.into_inner();
+
+            let result = match db
+                .send(CreateCampaign {
+                    campaign_name: campaign_data.campaign_name,
+                    ad_id: campaign_data.ad_id,
+                    user_id: user.id,
+                    budget: campaign_data.budget,
+                    slots: campaign_data.slots,
+                    price: campaign_data.price,
+                    include_pending: state.booking.blocks_pending,
+                    billing_unit: state.booking.billing_unit,
+                    payment_provider: state.payment_provider.clone(),
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "create_campaign"));
+            result
+                .map(|campaign| HttpResponse::Ok().json(campaign))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[get("/get_all")]
+pub async fn get_campaigns(
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(GetUserCampaigns {
+                    user_id: user.id,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "get_user_campaigns"));
+            result
+                .map(|campaigns| HttpResponse::Ok().json(campaigns))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[get("/{campaign_id}")]
+pub async fn get_campaign(
+    campaign_id: Path<Uuid>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(GetCampaign {
+                    campaign_id: campaign_id.into_inner(),
+                    user_id: user.id,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "get_campaign"));
+            result
+                .map(|campaign| HttpResponse::Ok().json(campaign))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
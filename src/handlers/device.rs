@@ -0,0 +1,122 @@
+use crate::actors::device::{
+    sha256_hex, GetDevicePlaylist, RecordHeartbeat, DEFAULT_PLAYLIST_HOURS,
+};
+use crate::errors::{AppError, AppErrorType};
+use crate::handlers::{blocking_error, log_error};
+use crate::media_store::{MediaLocation, MediaStore};
+use crate::models::app_state::AppState;
+use crate::models::device::{DeviceClaims, HeartbeatData, Playlist, PlaylistQuery};
+use actix_web::http::header::{self, EntityTag, IfNoneMatch};
+use actix_web::web::{self, Data, Header, Json, Query, ReqData};
+use actix_web::{get, post, HttpResponse, Responder};
+use slog::o;
+
+/// Players poll this often, so an unchanged playlist is answered with `304 Not Modified`.
+#[get("/playlist")]
+pub async fn get_playlist(
+    playlist_query: Query<PlaylistQuery>,
+    if_none_match: Option<Header<IfNoneMatch>>,
+    req: Option<ReqData<DeviceClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(device) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(GetDevicePlaylist {
+                    screen_id: device.screen_id,
+                    hours: playlist_query.hours.unwrap_or(DEFAULT_PLAYLIST_HOURS),
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "get_device_playlist"));
+            let playlist = result.map_err(log_error(sub_log.clone()))?;
+
+            let media_store = state.media_store.clone();
+            let playlist =
+                web::block(move || locate_playlist_media(media_store.as_ref(), playlist))
+                    .await
+                    .map_err(blocking_error)?
+                    .map_err(log_error(sub_log))?;
+
+            let body = serde_json::to_vec(&playlist).map_err(|err| {
+                AppError::new(
+                    None,
+                    Some(err.to_string()),
+                    AppErrorType::SomethingWentWrong,
+                )
+            })?;
+            let etag = EntityTag::new_strong(sha256_hex(&body));
+
+            let is_unchanged = match if_none_match.map(|header| header.into_inner()) {
+                Some(IfNoneMatch::Any) => true,
+                Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+                None => false,
+            };
+
+            if is_unchanged {
+                Ok(HttpResponse::NotModified()
+                    .insert_header(header::ETag(etag))
+                    .finish())
+            } else {
+                Ok(HttpResponse::Ok()
+                    .insert_header(header::ETag(etag))
+                    .content_type("application/json")
+                    .body(body))
+            }
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+/// Points the playlist at presigned URLs when media is kept in an object store, so players
+/// download it from there directly. Presigned URLs expire, so such a playlist changes its ETag
+/// every time it is signed again.
+fn locate_playlist_media(
+    media_store: &dyn MediaStore,
+    mut playlist: Playlist,
+) -> Result<Playlist, AppError> {
+    for item in &mut playlist.items {
+        if let Some(MediaLocation::Url(url)) = media_store.locate(&item.media_id)? {
+            item.media_url = url;
+        }
+    }
+    Ok(playlist)
+}
+
+#[post("/heartbeat")]
+pub async fn record_heartbeat(
+    heartbeat_data: Json<HeartbeatData>,
+    req: Option<ReqData<DeviceClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(device) => {
+            let db = state.as_ref().db.clone();
+            let heartbeat_data = heartbeat_data.into_inner();
+            let result = match db
+                .send(RecordHeartbeat {
+                    screen_id: device.screen_id,
+                    software_version: heartbeat_data.software_version,
+                    free_storage_bytes: heartbeat_data.free_storage_bytes,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "record_heartbeat"));
+            result
+                .map(|heartbeat| HttpResponse::Ok().json(heartbeat))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
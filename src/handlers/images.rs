@@ -1,63 +1,310 @@
-use crate::errors::AppError;
-use crate::handlers::log_io_error;
+use crate::actors::media::RecordMediaRenditions;
+use crate::actors::screens::GetScreenResolutions;
+use crate::errors::{AppError, AppErrorType};
+use crate::handlers::{blocking_error, log_error, log_io_error};
+use crate::media::rendition::{remove_renditions, render, Rendition, RenditionSize};
+use crate::media::{
+    canonical_media_id, detect_media_format, media_too_large, new_media_id, parse_media_id,
+    staged_upload_path, MediaFormat, SNIFF_LENGTH,
+};
+use crate::media_store::{MediaLocation, MediaStore};
 use crate::models::app_state::AppState;
-use actix_multipart::Multipart;
-use actix_web::web::Data;
-use actix_web::{get, web, HttpResponse};
+use crate::models::media::{media_url, MediaRendition, Renditions, UploadedMedia};
+use crate::models::screen_characteristics::MediaType;
+use actix_files::{file_extension_to_mime, NamedFile};
+use actix_multipart::{Field, Multipart, MultipartError};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::web::{Data, Path as UrlPath};
+use actix_web::{get, web, HttpRequest, HttpResponse};
 use futures_util::{StreamExt, TryStreamExt};
-use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
-use slog::{info, o};
+use slog::o;
 use std::io::Write;
 use std::path::Path;
-use uuid::Uuid;
+use std::sync::Arc;
 
-#[get("")]
-async fn get_image(img_url: String, state: Data<AppState>) -> Result<HttpResponse, AppError> {
-    if !Path::new(&img_url).exists() {
-        info!(state.logger, "NO");
-        info!(state.logger, "{}", img_url.to_string());
-    } else {
-        info!(state.logger, "OK");
-    }
+/// Media ids are random and a file never changes after upload, so caches can keep it for good.
+const MEDIA_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
 
-    let url = Path::new("media").join(img_url);
-    let result = tokio::fs::read(&url).await;
+/// Screen resolutions an ad image gets renditions for, on top of the standard sizes.
+const MAX_SCREEN_RENDITIONS: usize = 8;
 
+/// Serves stored media. Files on local disk get ETag and Last-Modified validators and byte range
+/// support, so conditional and partial requests are answered with 304 and 206. Media kept in an
+/// object store is served by redirecting to a presigned URL.
+#[get("/{media_id}")]
+async fn get_image(
+    media_id: UrlPath<String>,
+    req: HttpRequest,
+    state: Data<AppState>,
+) -> Result<HttpResponse, AppError> {
     let sub_log = state.logger.new(o!("handle" => "get image"));
 
-    result
-        .map(|image_bytes| {
-            let content_type = "image/png";
-            HttpResponse::Ok()
-                .content_type(content_type)
-                .body(image_bytes)
+    let (media_id, format) = match parse_media_id(&media_id) {
+        Some(parsed) => parsed,
+        None => return Err(AppError::new(None, None, AppErrorType::NotFoundError)),
+    };
+
+    let media_store = state.media_store.clone();
+    let location = web::block(move || media_store.locate(&media_id))
+        .await
+        .map_err(blocking_error)?
+        .map_err(log_error(sub_log.clone()))?;
+
+    match location {
+        Some(MediaLocation::File(file_path)) => {
+            let file = NamedFile::open_async(file_path)
+                .await
+                .map_err(log_io_error(sub_log))?;
+            let mut response = file
+                .set_content_type(file_extension_to_mime(format.extension()))
+                .into_response(&req);
+            response.headers_mut().insert(
+                header::CACHE_CONTROL,
+                HeaderValue::from_static(MEDIA_CACHE_CONTROL),
+            );
+            Ok(response)
+        }
+        // The presigned URL expires, so the redirect itself must not be cached.
+        Some(MediaLocation::Url(url)) => Ok(HttpResponse::TemporaryRedirect()
+            .insert_header((header::LOCATION, url))
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .finish()),
+        None => Err(AppError::new(None, None, AppErrorType::NotFoundError)),
+    }
+}
+
+/// Checks that a media id sent with an ad refers to media that was uploaded to the store. Ids of
+/// older uploads that are still spelled with percent-encoded dashes are accepted too.
+pub async fn check_stored_media(
+    media_store: Arc<dyn MediaStore>,
+    media_id: &str,
+) -> Result<(), AppError> {
+    let unknown_media = || {
+        AppError::new(
+            Some(format!("Media {} has not been uploaded", media_id)),
+            None,
+            AppErrorType::ValidationError,
+        )
+    };
+
+    let media_id = canonical_media_id(media_id).ok_or_else(unknown_media)?;
+    let exists = web::block(move || media_store.exists(&media_id))
+        .await
+        .map_err(blocking_error)??;
+
+    if exists {
+        Ok(())
+    } else {
+        Err(unknown_media())
+    }
+}
+
+/// Saves the first file of the upload as an image, together with its standard renditions.
+pub async fn save_files(payload: Multipart, state: &AppState) -> Result<UploadedMedia, AppError> {
+    save_media(
+        payload,
+        &[MediaType::Image],
+        RenditionSize::standard(),
+        state,
+    )
+    .await
+}
+
+/// Rendition sizes of ad images: the standard ones and the native resolutions of the most
+/// common active screens, so players don't have to scale images themselves.
+pub async fn ad_rendition_sizes(state: &AppState) -> Result<Vec<RenditionSize>, AppError> {
+    let resolutions = match state
+        .db
+        .send(GetScreenResolutions {
+            limit: MAX_SCREEN_RENDITIONS,
+            logger: state.logger.clone(),
         })
-        .map_err(log_io_error(sub_log))
+        .await
+    {
+        Ok(res) => res?,
+        Err(err) => return Err(AppError::from_mailbox(err)),
+    };
+
+    let mut sizes = RenditionSize::standard();
+    sizes.extend(resolutions.iter().filter_map(RenditionSize::for_screen));
+    Ok(sizes)
 }
 
-pub async fn save_files(mut payload: Multipart) -> Result<String, AppError> {
-    let mut paths = Vec::new();
-    while let Ok(Some(mut field)) = payload.try_next().await {
-        let file_name = get_random_file_name();
-        let file_path = format!("{}{}.png", "media/", file_name);
-        paths.push(file_path.clone());
+/// Saves the first file of the upload under a random name. The format is detected from the
+/// file's leading bytes, which also decide its extension and size limit. The upload is written to
+/// a temporary file first and handed to the media store once it is complete. Images also get a
+/// scaled down rendition for each of `sizes`, stored next to the original.
+pub async fn save_media(
+    mut payload: Multipart,
+    allowed: &[MediaType],
+    sizes: Vec<RenditionSize>,
+    state: &AppState,
+) -> Result<UploadedMedia, AppError> {
+    let mut field = match payload.try_next().await {
+        Ok(Some(field)) => field,
+        _ => {
+            return Err(AppError::new(
+                Some("No file was uploaded".to_string()),
+                None,
+                AppErrorType::ValidationError,
+            ))
+        }
+    };
+
+    let mut header = Vec::with_capacity(SNIFF_LENGTH);
+    while header.len() < SNIFF_LENGTH {
+        match field.next().await {
+            Some(chunk) => header.extend_from_slice(&chunk.map_err(upload_error)?),
+            None => break,
+        }
+    }
+
+    let format = detect_media_format(&header, allowed)?;
+    let media_id = new_media_id(format);
+    let staged_path = staged_upload_path(&media_id);
 
-        let mut f = web::block(|| std::fs::File::create(file_path))
+    let size_bytes = match write_media(&staged_path, header, &mut field, format).await {
+        Ok(size_bytes) => size_bytes,
+        Err(err) => {
+            // Don't leave a partial file behind when the upload is rejected half way.
+            let _ = std::fs::remove_file(&staged_path);
+            return Err(err);
+        }
+    };
+
+    let renditions = if format.media_type() == MediaType::Image && !sizes.is_empty() {
+        let source = staged_path.clone();
+        let original_id = media_id.clone();
+        let rendered = web::block(move || render(&source, &original_id, format, &sizes))
             .await
-            .unwrap()?;
+            .map_err(blocking_error)?;
+        match rendered {
+            Ok(renditions) => renditions,
+            Err(err) => {
+                let _ = std::fs::remove_file(&staged_path);
+                return Err(err);
+            }
+        }
+    } else {
+        Vec::new()
+    };
 
-        while let Some(chunk) = field.next().await {
-            let data = chunk.unwrap();
-            f = web::block(move || f.write_all(&data).map(|_| f))
-                .await
-                .unwrap()?;
+    let rendition_rows: Vec<MediaRendition> = renditions
+        .iter()
+        .map(|rendition| MediaRendition {
+            media_id: media_id.clone(),
+            rendition: rendition.name.clone(),
+            rendition_media_id: rendition.media_id.clone(),
+            width: rendition.width as i32,
+            height: rendition.height as i32,
+        })
+        .collect();
+
+    let media_store = state.media_store.clone();
+    let stored_media_id = media_id.clone();
+    let stored = web::block(move || {
+        let result = store_media(
+            media_store.as_ref(),
+            &stored_media_id,
+            format,
+            &staged_path,
+            &renditions,
+        );
+        if result.is_err() {
+            let _ = std::fs::remove_file(&staged_path);
+            remove_renditions(&renditions);
         }
+        result
+    })
+    .await
+    .map_err(blocking_error)?;
+    stored?;
+
+    let renditions: Renditions = rendition_rows
+        .iter()
+        .map(|row| (row.rendition.clone(), media_url(&row.rendition_media_id)))
+        .collect();
+
+    if !rendition_rows.is_empty() {
+        match state
+            .db
+            .send(RecordMediaRenditions {
+                renditions: rendition_rows,
+                logger: state.logger.clone(),
+            })
+            .await
+        {
+            Ok(res) => res?,
+            Err(err) => return Err(AppError::from_mailbox(err)),
+        };
+    }
+
+    Ok(UploadedMedia {
+        media_id,
+        content_type: format.content_type().to_string(),
+        media_type: format.media_type(),
+        size_bytes,
+        renditions,
+    })
+}
+
+/// Stores the renditions before the original, so media that is referenced by its id always has
+/// them in place.
+fn store_media(
+    media_store: &dyn MediaStore,
+    media_id: &str,
+    format: MediaFormat,
+    staged_path: &Path,
+    renditions: &[Rendition],
+) -> Result<(), AppError> {
+    for rendition in renditions {
+        media_store.put(
+            &rendition.media_id,
+            rendition.format.content_type(),
+            &rendition.path,
+        )?;
+    }
+    media_store.put(media_id, format.content_type(), staged_path)
+}
+
+async fn write_media(
+    file_path: &Path,
+    header: Vec<u8>,
+    field: &mut Field,
+    format: MediaFormat,
+) -> Result<u64, AppError> {
+    let mut size_bytes = header.len() as u64;
+    if size_bytes > format.max_size() {
+        return Err(media_too_large(format));
+    }
+
+    let file_path = file_path.to_path_buf();
+    let mut f = web::block(|| std::fs::File::create(file_path))
+        .await
+        .map_err(blocking_error)??;
+    f = web::block(move || f.write_all(&header).map(|_| f))
+        .await
+        .map_err(blocking_error)??;
+
+    while let Some(chunk) = field.next().await {
+        let data = chunk.map_err(upload_error)?;
+        size_bytes += data.len() as u64;
+        if size_bytes > format.max_size() {
+            return Err(media_too_large(format));
+        }
+
+        f = web::block(move || f.write_all(&data).map(|_| f))
+            .await
+            .map_err(blocking_error)??;
     }
 
-    Ok(paths.get(0).unwrap().clone())
+    Ok(size_bytes)
 }
 
-fn get_random_file_name() -> String {
-    let file_name = Uuid::new_v4().to_string();
-    utf8_percent_encode(&file_name, NON_ALPHANUMERIC).to_string()
+fn upload_error(err: MultipartError) -> AppError {
+    AppError::new(
+        Some("Upload could not be read".to_string()),
+        Some(err.to_string()),
+        AppErrorType::ValidationError,
+    )
 }
@@ -1,4 +1,6 @@
-use crate::errors::AppError;
+use crate::errors::{AppError, AppErrorType};
+use crate::middleware::token::{Role, TokenClaims};
+use actix_web::error::BlockingError;
 use slog::{error, o, Logger};
 use std::io;
 
@@ -6,10 +8,13 @@ pub mod ad;
 pub mod ad_order;
 pub mod admin;
 pub mod business;
+pub mod campaign;
 pub mod category;
+pub mod device;
 pub mod images;
 pub mod income;
 pub mod payment;
+pub mod play_event;
 pub mod screen;
 pub mod user;
 
@@ -25,7 +30,7 @@ fn log_io_error(log: Logger) -> impl Fn(io::Error) -> AppError {
             None => error!(log, "Something went wrong"),
         }
 
-        AppError::from(app_error)
+        app_error
     }
 }
 
@@ -39,6 +44,26 @@ fn log_error(log: Logger) -> impl Fn(AppError) -> AppError {
             None => error!(log, "Something went wrong"),
         }
 
-        AppError::from(err)
+        err
     }
 }
+
+fn blocking_error(err: BlockingError) -> AppError {
+    AppError::new(
+        None,
+        Some(err.to_string()),
+        AppErrorType::SomethingWentWrong,
+    )
+}
+
+/// Only admins may ask to act on resources that belong to someone else.
+fn check_admin_override(admin_override: bool, claims: &TokenClaims) -> Result<(), AppError> {
+    if admin_override && !claims.roles.contains(&Role::Admin) {
+        return Err(AppError::new(
+            Some("Only admins can override ownership checks".to_string()),
+            None,
+            AppErrorType::ForbiddenError,
+        ));
+    }
+    Ok(())
+}
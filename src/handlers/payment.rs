@@ -1 +1,144 @@
+use crate::actors::payment::{ApplyPaymentEvent, CreatePayment, GetUserPayments, RefundPayment};
+use crate::errors::AppError;
+use crate::handlers::log_error;
+use crate::middleware::token::TokenClaims;
+use crate::models::app_state::AppState;
+use crate::models::payment::{PaymentData, PaymentId, PaymentWebhookEvent};
+use actix_web::web::{Bytes, Data, Json, ReqData};
+use actix_web::{get, post, HttpRequest, HttpResponse, Responder};
+use slog::o;
 
+const WEBHOOK_SIGNATURE_HEADER: &str = "X-Payment-Signature";
+
+#[post("/create")]
+pub async fn create(
+    payment_data: Json<PaymentData>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let payment_data = payment_data.into_inner();
+
+            let result = match db
+                .send(CreatePayment {
+                    user_id: user.id,
+                    ad_order_id: payment_data.order_id,
+                    price: payment_data.price,
+                    payment_provider: state.payment_provider.clone(),
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "create_payment"));
+            result
+                .map(|payment| HttpResponse::Ok().json(payment))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[get("/get_all")]
+pub async fn get_payments(
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(GetUserPayments {
+                    user_id: user.id,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "get_user_payments"));
+            result
+                .map(|payments| HttpResponse::Ok().json(payments))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[post("/refund")]
+pub async fn refund(
+    payment_id: Json<PaymentId>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(RefundPayment {
+                    payment_id: payment_id.into_inner().payment_id,
+                    user_id: user.id,
+                    payment_provider: state.payment_provider.clone(),
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "refund_payment"));
+            result
+                .map(|payment| HttpResponse::Ok().json(payment))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[post("/webhook")]
+pub async fn webhook(
+    req: HttpRequest,
+    body: Bytes,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let signature = req
+        .headers()
+        .get(WEBHOOK_SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    if !state.payment_provider.verify_webhook(&body, signature) {
+        return Ok(HttpResponse::Unauthorized().json("Invalid webhook signature"));
+    }
+
+    let event: PaymentWebhookEvent = match serde_json::from_slice(&body) {
+        Ok(event) => event,
+        Err(_) => return Ok(HttpResponse::BadRequest().json("Invalid webhook payload")),
+    };
+
+    let db = state.as_ref().db.clone();
+    let result = match db
+        .send(ApplyPaymentEvent {
+            provider_reference: event.provider_reference,
+            status: event.status,
+            logger: state.logger.clone(),
+        })
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => return Err(AppError::from_mailbox(err)),
+    };
+
+    let sub_log = state.logger.new(o!("handle" => "payment_webhook"));
+    result
+        .map(|res| HttpResponse::Ok().json(res))
+        .map_err(log_error(sub_log))
+}
@@ -0,0 +1,102 @@
+use crate::actors::play_event::{
+    GetBusinessDeliveryReports, GetUserDeliveryReports, RecordPlayEvents,
+};
+use crate::errors::AppError;
+use crate::handlers::log_error;
+use crate::middleware::token::TokenClaims;
+use crate::models::app_state::AppState;
+use crate::models::device::DeviceClaims;
+use crate::models::play_event::PlayEventData;
+use actix_web::web::{Data, Json, ReqData};
+use actix_web::{get, post, HttpResponse, Responder};
+use slog::o;
+
+#[post("/play_events")]
+pub async fn record_play_events(
+    events: Json<Vec<PlayEventData>>,
+    req: Option<ReqData<DeviceClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(device) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(RecordPlayEvents {
+                    screen_id: device.screen_id,
+                    events: events.into_inner(),
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "record_play_events"));
+            result
+                .map(|batch_result| HttpResponse::Ok().json(batch_result))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[get("/delivery_reports")]
+pub async fn get_user_delivery_reports(
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(user) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(GetUserDeliveryReports {
+                    user_id: user.id,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state
+                .logger
+                .new(o!("handle" => "get_user_delivery_reports"));
+            result
+                .map(|reports| HttpResponse::Ok().json(reports))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[get("/delivery_reports")]
+pub async fn get_business_delivery_reports(
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(business) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(GetBusinessDeliveryReports {
+                    business_id: business.id,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state
+                .logger
+                .new(o!("handle" => "get_business_delivery_reports"));
+            result
+                .map(|reports| HttpResponse::Ok().json(reports))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
@@ -1,22 +1,32 @@
 use crate::actors::address::GetAllAddresses;
+use crate::actors::device::{GetBusinessScreenHealth, IssueDeviceKey};
 use crate::actors::screens::{
-    GetAllScreens, GetAllScreensByBusinessId, GetOptimalScreens, GetScreenDataById,
+    CreateScreen, DeleteScreen, GetAllScreens, GetAllScreensByBusinessId, GetNearbyScreens,
+    GetOptimalScreens, GetScreenAvailability, GetScreenDataById, UpdateScreen,
 };
 use crate::errors::AppError;
 use crate::handlers::log_error;
 use crate::middleware::token::TokenClaims;
 use crate::models::app_state::AppState;
-use crate::models::screen::{OptimalScreensData, ScreenId};
-use actix_web::web::{Data, Json, ReqData};
+use crate::models::screen::{
+    AvailabilityQuery, BusinessScreenData, NearbyScreensQuery, OptimalScreensData, ScreenId,
+    ScreenStatus, ScreenUpdateData,
+};
+use crate::models::screen_characteristics::ScreenFilter;
+use actix_web::web::{Data, Json, Path, Query, ReqData};
 use actix_web::{get, post, HttpResponse, Responder};
 use slog::o;
 use uuid::Uuid;
 
 #[get("/get_all")]
-pub async fn get_all(state: Data<AppState>) -> Result<impl Responder, AppError> {
+pub async fn get_all(
+    state: Data<AppState>,
+    filter: Query<ScreenFilter>,
+) -> Result<impl Responder, AppError> {
     let db = state.as_ref().db.clone();
     let result = match db
         .send(GetAllScreens {
+            filter: filter.into_inner(),
             logger: state.logger.clone(),
         })
         .await
@@ -95,6 +105,7 @@ pub async fn get_screen_data_by_id(
     let result = match db
         .send(GetScreenDataById {
             screen_id: screen_id.screen_id,
+            offline_after_micros: state.screens.offline_after_micros(),
             logger: state.logger.clone(),
         })
         .await
@@ -140,6 +151,10 @@ pub async fn find_optimal_screens(
         .send(GetOptimalScreens {
             user_budget: opt_screens_data.user_budget,
             ad_category_ids: opt_screens_data.ad_category_ids,
+            start_time: opt_screens_data.start_time,
+            end_time: opt_screens_data.end_time,
+            include_pending: state.booking.blocks_pending,
+            billing_unit: state.booking.billing_unit,
             logger: state.logger.clone(),
         })
         .await
@@ -150,6 +165,234 @@ pub async fn find_optimal_screens(
 
     let sub_log = state.logger.new(o!("handle" => "find_optimal_screens"));
     result
-        .map(|screen_data| HttpResponse::Ok().json(screen_data))
+        .map(|selection| HttpResponse::Ok().json(selection))
+        .map_err(log_error(sub_log))
+}
+
+#[get("/{screen_id}/availability")]
+pub async fn get_availability(
+    screen_id: Path<Uuid>,
+    availability_query: Query<AvailabilityQuery>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let db = state.as_ref().db.clone();
+    let availability_query = availability_query.into_inner();
+
+    let result = match db
+        .send(GetScreenAvailability {
+            screen_id: screen_id.into_inner(),
+            from: availability_query.from,
+            to: availability_query.to,
+            slot_minutes: availability_query.slot_minutes,
+            include_pending: state.booking.blocks_pending,
+            logger: state.logger.clone(),
+        })
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => return Err(AppError::from_mailbox(err)),
+    };
+
+    let sub_log = state.logger.new(o!("handle" => "get_screen_availability"));
+    result
+        .map(|availability| HttpResponse::Ok().json(availability))
+        .map_err(log_error(sub_log))
+}
+
+#[get("/nearby")]
+pub async fn get_nearby(
+    nearby_query: Query<NearbyScreensQuery>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let db = state.as_ref().db.clone();
+    let nearby_query = nearby_query.into_inner();
+
+    let result = match db
+        .send(GetNearbyScreens {
+            latitude: nearby_query.lat,
+            longitude: nearby_query.lng,
+            radius_km: nearby_query.radius_km,
+            logger: state.logger.clone(),
+        })
+        .await
+    {
+        Ok(res) => res,
+        Err(err) => return Err(AppError::from_mailbox(err)),
+    };
+
+    let sub_log = state.logger.new(o!("handle" => "get_nearby_screens"));
+    result
+        .map(|nearby_screens| HttpResponse::Ok().json(nearby_screens))
         .map_err(log_error(sub_log))
 }
+
+#[post("/create")]
+pub async fn create_business_screen(
+    screen_data: Json<BusinessScreenData>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(business) => {
+            let db = state.as_ref().db.clone();
+            let screen_data = screen_data.into_inner();
+
+            let status = if state.screens.approval_required {
+                ScreenStatus::Pending
+            } else {
+                ScreenStatus::Active
+            };
+
+            let result = match db
+                .send(CreateScreen {
+                    name: screen_data.screen_name,
+                    price_per_time: screen_data.price_per_time,
+                    characteristics: screen_data.characteristics,
+                    traffic: screen_data.traffic,
+                    business_id: business.id,
+                    address_id: screen_data.address_id,
+                    status,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "create_business_screen"));
+            result
+                .map(|screen| HttpResponse::Ok().json(screen))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[post("/update")]
+pub async fn update_business_screen(
+    screen_data: Json<ScreenUpdateData>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(business) => {
+            let db = state.as_ref().db.clone();
+            let screen_data = screen_data.into_inner();
+
+            let result = match db
+                .send(UpdateScreen {
+                    id: screen_data.screen_id,
+                    business_id: business.id,
+                    name: screen_data.screen_name,
+                    price_per_time: screen_data.price_per_time,
+                    characteristics: screen_data.characteristics,
+                    traffic: screen_data.traffic,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "update_business_screen"));
+            result
+                .map(|screen| HttpResponse::Ok().json(screen))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[post("/delete")]
+pub async fn delete_business_screen(
+    screen_id: Json<ScreenId>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(business) => {
+            let db = state.as_ref().db.clone();
+
+            let result = match db
+                .send(DeleteScreen {
+                    id: screen_id.into_inner().screen_id,
+                    business_id: business.id,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "delete_business_screen"));
+            result
+                .map(|deletion| HttpResponse::Ok().json(deletion))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[post("/device_key")]
+pub async fn issue_device_key(
+    screen_id: Json<ScreenId>,
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(business) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(IssueDeviceKey {
+                    screen_id: screen_id.into_inner().screen_id,
+                    business_id: business.id,
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state.logger.new(o!("handle" => "issue_device_key"));
+            result
+                .map(|device_key| HttpResponse::Ok().json(device_key))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
+
+#[get("/dashboard")]
+pub async fn get_screen_dashboard(
+    req: Option<ReqData<TokenClaims>>,
+    state: Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    match req {
+        Some(business) => {
+            let db = state.as_ref().db.clone();
+            let result = match db
+                .send(GetBusinessScreenHealth {
+                    business_id: business.id,
+                    offline_after_micros: state.screens.offline_after_micros(),
+                    logger: state.logger.clone(),
+                })
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => return Err(AppError::from_mailbox(err)),
+            };
+
+            let sub_log = state
+                .logger
+                .new(o!("handle" => "get_business_screen_health"));
+            result
+                .map(|screen_health| HttpResponse::Ok().json(screen_health))
+                .map_err(log_error(sub_log))
+        }
+        _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
+    }
+}
@@ -5,6 +5,7 @@ use crate::handlers::images::save_files;
 use crate::handlers::log_error;
 use crate::middleware::token::TokenClaims;
 use crate::models::app_state::AppState;
+use crate::models::media::ImageInfo;
 use crate::models::user::UserData;
 use actix_multipart::Multipart;
 use actix_web::web::{Data, Json, ReqData};
@@ -70,11 +71,11 @@ pub async fn change_img(
 ) -> Result<impl Responder, AppError> {
     match req {
         Some(user) => {
-            let img_url = save_files(payload).await?;
+            let uploaded_media = save_files(payload, &state).await?;
 
             let change_img = ChangeImg {
                 user_id: user.id,
-                img_url,
+                img_url: uploaded_media.media_id,
             };
 
             let db = state.as_ref().db.clone();
@@ -86,7 +87,12 @@ pub async fn change_img(
             let sub_log = state.logger.new(o!("handle" => "change img for business"));
 
             result
-                .map(|img_url| HttpResponse::Ok().json(img_url))
+                .map(|img_url| {
+                    HttpResponse::Ok().json(ImageInfo {
+                        img_url,
+                        img_renditions: uploaded_media.renditions,
+                    })
+                })
                 .map_err(log_error(sub_log))
         }
         _ => Ok(HttpResponse::Unauthorized().json("Unable to verify identity")),
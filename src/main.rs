@@ -6,12 +6,21 @@ mod actors;
 mod config;
 mod db_utils;
 mod errors;
+mod geo;
 mod handlers;
+mod media;
+mod media_store;
 mod middleware;
 mod models;
+mod payment_provider;
 mod schema;
 
+use crate::actors::ad_order::SyncAdOrderStatuses;
+use crate::actors::device::FlagOfflineScreenOrders;
+use crate::actors::payment::CaptureApprovedPayments;
 use crate::config::Config;
+use crate::media_store::gc::collect_media_garbage;
+use crate::middleware::device::device_validator;
 use crate::middleware::token::validator;
 use crate::middleware::token::Role::{Admin, Business as BusinessRole, Client};
 use crate::models::app_state::AppState;
@@ -20,7 +29,13 @@ use actix_web::web::Data;
 use actix_web::{web, App, HttpServer};
 use actix_web_httpauth::middleware::HttpAuthentication;
 use dotenv::dotenv;
-use slog::info;
+use slog::{error, info};
+use std::time::Duration;
+
+/// How often approved orders are moved to running and running ones to completed, payments of
+/// approved orders whose capture failed are captured, and running orders on offline screens are
+/// flagged.
+const AD_ORDER_STATUS_SYNC_INTERVAL: Duration = Duration::from_secs(60);
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -33,6 +48,97 @@ async fn main() -> std::io::Result<()> {
         "Starting server at http://{}:{}", config.server.host, config.server.port
     );
 
+    let db = config.db.clone();
+    let screen_config = config.screens;
+    let payment_provider = config.payment_provider.clone();
+    let sync_logger = logger.clone();
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(AD_ORDER_STATUS_SYNC_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = db
+                .send(SyncAdOrderStatuses {
+                    logger: sync_logger.clone(),
+                })
+                .await;
+
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => error!(sync_logger, "Failed to sync ad order statuses: {}", err),
+                Err(err) => error!(sync_logger, "Failed to sync ad order statuses: {}", err),
+            }
+
+            let result = db
+                .send(CaptureApprovedPayments {
+                    payment_provider: payment_provider.clone(),
+                    logger: sync_logger.clone(),
+                })
+                .await;
+
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => error!(sync_logger, "Failed to capture payments: {}", err),
+                Err(err) => error!(sync_logger, "Failed to capture payments: {}", err),
+            }
+
+            let result = db
+                .send(FlagOfflineScreenOrders {
+                    offline_after_micros: screen_config.offline_after_micros(),
+                    logger: sync_logger.clone(),
+                })
+                .await;
+
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => {
+                    error!(sync_logger, "Failed to flag offline screen orders: {}", err)
+                }
+                Err(err) => error!(sync_logger, "Failed to flag offline screen orders: {}", err),
+            }
+        }
+    });
+
+    let db = config.db.clone();
+    let media_store = config.media_store.clone();
+    let media_gc_config = config.media_gc;
+    let media_gc_logger = logger.clone();
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(media_gc_config.interval());
+        loop {
+            interval.tick().await;
+            let result = collect_media_garbage(
+                &db,
+                media_store.clone(),
+                media_gc_config,
+                media_gc_config.dry_run,
+                &media_gc_logger,
+            )
+            .await;
+
+            match result {
+                Ok(report) if report.dry_run => {
+                    for orphan in &report.orphans {
+                        info!(
+                            media_gc_logger,
+                            "Would delete orphaned media {}", orphan.key
+                        );
+                    }
+                    for upload in &report.abandoned_uploads {
+                        info!(media_gc_logger, "Would delete abandoned upload {}", upload);
+                    }
+                }
+                Ok(report) => info!(
+                    media_gc_logger,
+                    "Deleted {} orphaned media ({} bytes) and {} abandoned uploads",
+                    report.orphans.len(),
+                    report.orphaned_bytes,
+                    report.abandoned_uploads.len()
+                ),
+                Err(err) => error!(media_gc_logger, "Failed to collect media garbage: {}", err),
+            }
+        }
+    });
+
     HttpServer::new(move || {
         let bearer_middleware = HttpAuthentication::bearer(validator);
         let cors = Cors::default()
@@ -44,10 +150,16 @@ async fn main() -> std::io::Result<()> {
             .app_data(Data::new(AppState {
                 db: config.db.clone(),
                 logger: logger.clone(),
+                payment_provider: config.payment_provider.clone(),
+                media_store: config.media_store.clone(),
+                booking: config.booking,
+                screens: config.screens,
+                media_gc: config.media_gc,
             }))
             .wrap(cors)
             .wrap(actix_web::middleware::Logger::default())
             .service(web::scope("/images").service(handlers::images::get_image))
+            .service(web::scope("/payments").service(handlers::payment::webhook))
             .service(
                 web::scope("/categories")
                     .wrap(bearer_middleware.clone())
@@ -64,7 +176,25 @@ async fn main() -> std::io::Result<()> {
                             .wrap(bearer_middleware.clone())
                             .app_data(Data::new(vec![Client, Admin]))
                             .service(handlers::user::change_img)
-                            .service(handlers::ad_order::create_ad_order),
+                            .service(handlers::ad_order::create_ad_order)
+                            .service(handlers::ad_order::quote_ad_order)
+                            .service(handlers::ad_order::create_recurring_ad_order)
+                            .service(handlers::ad_order::preview_recurring_ad_order)
+                            .service(handlers::ad_order::cancel_ad_order)
+                            .service(handlers::ad_order::get_user_ad_orders)
+                            .service(handlers::play_event::get_user_delivery_reports)
+                            .service(
+                                web::scope("/campaigns")
+                                    .service(handlers::campaign::create)
+                                    .service(handlers::campaign::get_campaigns)
+                                    .service(handlers::campaign::get_campaign),
+                            )
+                            .service(
+                                web::scope("/payments")
+                                    .service(handlers::payment::create)
+                                    .service(handlers::payment::get_payments)
+                                    .service(handlers::payment::refund),
+                            ),
                     ),
             )
             .service(
@@ -73,7 +203,8 @@ async fn main() -> std::io::Result<()> {
                     .service(handlers::ad::create)
                     .service(handlers::ad::get_ads)
                     .service(handlers::ad::get_user_ads)
-                    .service(handlers::ad::update),
+                    .service(handlers::ad::update)
+                    .service(handlers::ad::upload_media),
             )
             .service(
                 web::scope("/screens")
@@ -85,7 +216,9 @@ async fn main() -> std::io::Result<()> {
                             .service(handlers::screen::get_screen_data_by_id)
                             .service(handlers::screen::get_all_business_screens)
                             .service(handlers::screen::get_all_by_business_id)
-                            .service(handlers::screen::get_all_addresses),
+                            .service(handlers::screen::get_all_addresses)
+                            .service(handlers::screen::get_availability)
+                            .service(handlers::screen::get_nearby),
                     ),
             )
             .service(
@@ -106,9 +239,25 @@ async fn main() -> std::io::Result<()> {
                             .service(handlers::business::change_img)
                             .service(handlers::business::change_business_info)
                             .service(handlers::ad_order::reject_ad_order)
-                            .service(handlers::ad_order::approve_ad_order),
+                            .service(handlers::ad_order::approve_ad_order)
+                            .service(handlers::play_event::get_business_delivery_reports)
+                            .service(
+                                web::scope("/screens")
+                                    .service(handlers::screen::create_business_screen)
+                                    .service(handlers::screen::update_business_screen)
+                                    .service(handlers::screen::delete_business_screen)
+                                    .service(handlers::screen::issue_device_key)
+                                    .service(handlers::screen::get_screen_dashboard),
+                            ),
                     ),
             )
+            .service(
+                web::scope("/devices")
+                    .wrap(HttpAuthentication::bearer(device_validator))
+                    .service(handlers::device::get_playlist)
+                    .service(handlers::device::record_heartbeat)
+                    .service(handlers::play_event::record_play_events),
+            )
             .service(
                 web::scope("/admin")
                     .service(handlers::admin::register)
@@ -119,7 +268,10 @@ async fn main() -> std::io::Result<()> {
                             .app_data(Data::new(vec![Admin]))
                             .service(handlers::admin::create_screen)
                             .service(handlers::admin::create_address)
-                            .service(handlers::admin::change_ad_status),
+                            .service(handlers::admin::change_ad_status)
+                            .service(handlers::admin::change_screen_status)
+                            .service(handlers::admin::get_audit_logs)
+                            .service(handlers::admin::get_media_gc_report),
                     ),
             )
     })
@@ -0,0 +1,346 @@
+use crate::errors::{AppError, AppErrorType};
+use crate::models::screen_characteristics::MediaType;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+pub mod rendition;
+
+/// Bytes read from the start of an upload before its format is decided.
+pub const SNIFF_LENGTH: usize = 64;
+
+/// Uploads and their renditions are written to the temp directory under this prefix before they
+/// are handed to the media store.
+pub const STAGED_UPLOAD_PREFIX: &str = "upload-";
+
+const MEGABYTE: u64 = 1024 * 1024;
+
+/// ISO base media brands that are plain MP4 video. HEIF and AVIF images use the same container,
+/// so the brand has to be checked as well as the `ftyp` box.
+const MP4_BRANDS: [&[u8; 4]; 10] = [
+    b"isom", b"iso2", b"iso4", b"iso5", b"iso6", b"mp41", b"mp42", b"avc1", b"dash", b"M4V ",
+];
+
+/// File formats accepted as uploaded media, recognised by their leading bytes rather than by the
+/// name or content type the client sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaFormat {
+    Png,
+    Jpeg,
+    WebP,
+    Gif,
+    Mp4,
+    WebM,
+}
+
+impl MediaFormat {
+    pub fn sniff(bytes: &[u8]) -> Option<MediaFormat> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(MediaFormat::Png)
+        } else if bytes.starts_with(b"\xff\xd8\xff") {
+            Some(MediaFormat::Jpeg)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(MediaFormat::Gif)
+        } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(MediaFormat::WebP)
+        } else if bytes.len() >= 12
+            && &bytes[4..8] == b"ftyp"
+            && MP4_BRANDS.iter().any(|brand| &bytes[8..12] == *brand)
+        {
+            Some(MediaFormat::Mp4)
+        } else if bytes.starts_with(b"\x1a\x45\xdf\xa3")
+            && bytes.windows(4).any(|window| window == b"webm")
+        {
+            // Matroska files share the EBML header, only the doc type tells WebM apart.
+            Some(MediaFormat::WebM)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<MediaFormat> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(MediaFormat::Png),
+            "jpg" | "jpeg" => Some(MediaFormat::Jpeg),
+            "webp" => Some(MediaFormat::WebP),
+            "gif" => Some(MediaFormat::Gif),
+            "mp4" => Some(MediaFormat::Mp4),
+            "webm" => Some(MediaFormat::WebM),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MediaFormat::Png => "png",
+            MediaFormat::Jpeg => "jpg",
+            MediaFormat::WebP => "webp",
+            MediaFormat::Gif => "gif",
+            MediaFormat::Mp4 => "mp4",
+            MediaFormat::WebM => "webm",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            MediaFormat::Png => "image/png",
+            MediaFormat::Jpeg => "image/jpeg",
+            MediaFormat::WebP => "image/webp",
+            MediaFormat::Gif => "image/gif",
+            MediaFormat::Mp4 => "video/mp4",
+            MediaFormat::WebM => "video/webm",
+        }
+    }
+
+    pub fn media_type(&self) -> MediaType {
+        match self {
+            MediaFormat::Png | MediaFormat::Jpeg | MediaFormat::WebP | MediaFormat::Gif => {
+                MediaType::Image
+            }
+            MediaFormat::Mp4 | MediaFormat::WebM => MediaType::Video,
+        }
+    }
+
+    /// Largest upload accepted for the format, in bytes.
+    pub fn max_size(&self) -> u64 {
+        match self {
+            MediaFormat::Png | MediaFormat::Jpeg | MediaFormat::WebP => 10 * MEGABYTE,
+            MediaFormat::Gif => 20 * MEGABYTE,
+            MediaFormat::Mp4 | MediaFormat::WebM => 200 * MEGABYTE,
+        }
+    }
+}
+
+/// Creates the id a new upload is stored under.
+pub fn new_media_id(format: MediaFormat) -> String {
+    format!("{}.{}", Uuid::new_v4(), format.extension())
+}
+
+pub fn staged_upload_path(media_id: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}{}", STAGED_UPLOAD_PREFIX, media_id))
+}
+
+/// Checks that a media id has the `<uuid>.<extension>` form of stored media, or the
+/// `<uuid>_<rendition>.<extension>` form of one of its renditions, and returns it in canonical
+/// form, so ids from requests can be used as file names and object keys.
+pub fn parse_media_id(media_id: &str) -> Option<(String, MediaFormat)> {
+    let (stem, extension) = media_id.rsplit_once('.')?;
+    let format = MediaFormat::from_extension(extension)?;
+    let (uuid, rendition) = match stem.split_once('_') {
+        Some((uuid, rendition)) if is_rendition_name(rendition) => (uuid, Some(rendition)),
+        Some(_) => return None,
+        None => (stem, None),
+    };
+    let id = Uuid::parse_str(uuid).ok()?;
+
+    let canonical = match rendition {
+        Some(rendition) => format!("{}_{}.{}", id, rendition, format.extension()),
+        None => format!("{}.{}", id, format.extension()),
+    };
+    Some((canonical, format))
+}
+
+/// Canonical media id of a stored file name or `img_url`, including the percent-encoded names
+/// older uploads were saved under.
+pub fn canonical_media_id(name: &str) -> Option<String> {
+    parse_media_id(&name.replace("%2D", "-")).map(|(media_id, _)| media_id)
+}
+
+/// Media id of a rendition of the canonical `media_id`, stored in `format`.
+pub fn rendition_media_id(media_id: &str, rendition: &str, format: MediaFormat) -> String {
+    let stem = media_id.split_once('.').map_or(media_id, |(stem, _)| stem);
+    format!("{}_{}.{}", stem, rendition, format.extension())
+}
+
+fn is_rendition_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit())
+}
+
+/// Works out the format of an upload from its first bytes and checks it is one of the allowed
+/// media types.
+pub fn detect_media_format(header: &[u8], allowed: &[MediaType]) -> Result<MediaFormat, AppError> {
+    match MediaFormat::sniff(header) {
+        Some(format) if allowed.contains(&format.media_type()) => Ok(format),
+        _ => {
+            let accepted: Vec<&str> = [
+                MediaFormat::Png,
+                MediaFormat::Jpeg,
+                MediaFormat::WebP,
+                MediaFormat::Gif,
+                MediaFormat::Mp4,
+                MediaFormat::WebM,
+            ]
+            .iter()
+            .filter(|format| allowed.contains(&format.media_type()))
+            .map(|format| format.content_type())
+            .collect();
+
+            Err(AppError::new(
+                Some(format!(
+                    "Unsupported media, expected one of: {}",
+                    accepted.join(", ")
+                )),
+                None,
+                AppErrorType::UnsupportedMediaError,
+            ))
+        }
+    }
+}
+
+pub fn media_too_large(format: MediaFormat) -> AppError {
+    AppError::new(
+        Some(format!(
+            "{} files can be at most {} MB",
+            format.content_type(),
+            format.max_size() / MEGABYTE
+        )),
+        None,
+        AppErrorType::MediaTooLargeError,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR";
+    const JPEG: &[u8] = b"\xff\xd8\xff\xe0\0\x10JFIF\0";
+    const GIF: &[u8] = b"GIF89a\x01\0\x01\0\x80\0\0";
+    const WEBP: &[u8] = b"RIFF\x24\0\0\0WEBPVP8 ";
+    const MP4: &[u8] = b"\0\0\0\x20ftypisom\0\0\x02\0";
+    const WEBM: &[u8] = b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\x82\x84webm";
+
+    #[test]
+    fn sniffs_every_supported_signature() {
+        assert_eq!(MediaFormat::sniff(PNG), Some(MediaFormat::Png));
+        assert_eq!(MediaFormat::sniff(JPEG), Some(MediaFormat::Jpeg));
+        assert_eq!(MediaFormat::sniff(GIF), Some(MediaFormat::Gif));
+        assert_eq!(MediaFormat::sniff(b"GIF87a\x01\0"), Some(MediaFormat::Gif));
+        assert_eq!(MediaFormat::sniff(WEBP), Some(MediaFormat::WebP));
+        assert_eq!(MediaFormat::sniff(MP4), Some(MediaFormat::Mp4));
+        assert_eq!(MediaFormat::sniff(WEBM), Some(MediaFormat::WebM));
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        for signature in [PNG, JPEG, GIF, WEBP, MP4, WEBM] {
+            assert_eq!(MediaFormat::sniff(&signature[..2]), None);
+        }
+        assert_eq!(MediaFormat::sniff(b""), None);
+        assert_eq!(MediaFormat::sniff(b"\x89PNG\r\n\x1a"), None);
+        assert_eq!(MediaFormat::sniff(b"RIFF\x24\0\0\0WEB"), None);
+        assert_eq!(MediaFormat::sniff(b"\0\0\0\x20ftypis"), None);
+        // An EBML header without a doc type could be any Matroska file.
+        assert_eq!(MediaFormat::sniff(&WEBM[..4]), None);
+    }
+
+    #[test]
+    fn rejects_lookalike_containers() {
+        // HEIF images and Matroska video share the MP4 and WebM containers.
+        assert_eq!(MediaFormat::sniff(b"\0\0\0\x18ftypheic\0\0\0\0"), None);
+        assert_eq!(
+            MediaFormat::sniff(b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\x82\x88matroska"),
+            None
+        );
+        assert_eq!(MediaFormat::sniff(b"RIFF\x24\0\0\0WAVEfmt "), None);
+    }
+
+    #[test]
+    fn goes_by_the_leading_bytes_only() {
+        // A GIF with a script appended is still only ever stored and served as a GIF.
+        let polyglot = [GIF, b"<script>alert(1)</script>".as_slice()].concat();
+        assert_eq!(MediaFormat::sniff(&polyglot), Some(MediaFormat::Gif));
+
+        // A PNG uploaded as `photo.jpg` is stored under the extension of what it really is.
+        let format = MediaFormat::sniff(PNG).unwrap();
+        assert!(new_media_id(format).ends_with(".png"));
+
+        // Markup is refused however the client named it.
+        assert_eq!(
+            MediaFormat::sniff(b"<svg xmlns=\"http://www.w3.org/2000/svg\">"),
+            None
+        );
+        assert_eq!(MediaFormat::sniff(b"<!DOCTYPE html><html>"), None);
+    }
+
+    #[test]
+    fn detects_only_allowed_media_types() {
+        assert_eq!(
+            detect_media_format(PNG, &[MediaType::Image]).ok(),
+            Some(MediaFormat::Png)
+        );
+        assert!(detect_media_format(MP4, &[MediaType::Image])
+            .is_err_and(|err| matches!(err.error_type, AppErrorType::UnsupportedMediaError)));
+        assert_eq!(
+            detect_media_format(MP4, &[MediaType::Image, MediaType::Video]).ok(),
+            Some(MediaFormat::Mp4)
+        );
+    }
+
+    const ID: &str = "3f2504e0-4f89-41d3-9a0c-0305e82c3301";
+
+    #[test]
+    fn parses_media_ids_into_canonical_form() {
+        assert_eq!(
+            parse_media_id(&format!("{}.png", ID)),
+            Some((format!("{}.png", ID), MediaFormat::Png))
+        );
+        assert_eq!(
+            parse_media_id(&format!("{}.JPEG", ID.to_uppercase())),
+            Some((format!("{}.jpg", ID), MediaFormat::Jpeg))
+        );
+        assert_eq!(
+            parse_media_id(&format!("{}_1920x1080.jpg", ID)),
+            Some((format!("{}_1920x1080.jpg", ID), MediaFormat::Jpeg))
+        );
+    }
+
+    #[test]
+    fn rejects_media_ids_that_could_leave_the_store() {
+        for media_id in [
+            format!("../{}.png", ID),
+            format!("{}/../{}.png", ID, ID),
+            format!("..{}.png", ID),
+            format!("media/{}.png", ID),
+            format!("/{}.png", ID),
+            format!("%2F{}.png", ID),
+            format!("{}%2F.png", ID),
+            format!("%2E%2E%2F{}.png", ID),
+            format!("{}%2Epng", ID),
+            format!("{}_../x.png", ID),
+            "..".to_string(),
+            ".png".to_string(),
+        ] {
+            assert_eq!(parse_media_id(&media_id), None, "{}", media_id);
+            assert_eq!(canonical_media_id(&media_id), None, "{}", media_id);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_extensions_and_bad_rendition_names() {
+        for media_id in [
+            format!("{}.svg", ID),
+            format!("{}.html", ID),
+            format!("{}.png.exe", ID),
+            ID.to_string(),
+            format!("{}_.png", ID),
+            format!("{}_THUMB.png", ID),
+            format!("{}_a_b.png", ID),
+            format!("{}_1920-1080.png", ID),
+            format!("{}_%2e%2e.png", ID),
+        ] {
+            assert_eq!(parse_media_id(&media_id), None, "{}", media_id);
+        }
+    }
+
+    #[test]
+    fn decodes_the_dashes_of_older_file_names() {
+        let encoded = format!("{}.png", ID.replace('-', "%2D"));
+        assert_eq!(canonical_media_id(&encoded), Some(format!("{}.png", ID)));
+        // Only dashes were ever encoded, so other escapes stay invalid.
+        let encoded_dot = format!("{}%2Epng", ID.replace('-', "%2D"));
+        assert_eq!(canonical_media_id(&encoded_dot), None);
+    }
+}
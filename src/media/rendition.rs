@@ -0,0 +1,165 @@
+use crate::errors::{AppError, AppErrorType};
+use crate::media::{rendition_media_id, staged_upload_path, MediaFormat};
+use crate::models::screen_characteristics::Resolution;
+use image::imageops::FilterType;
+use image::io::{Limits, Reader};
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Images with a longer side are refused instead of being decoded.
+const MAX_DECODED_SIDE: u32 = 16384;
+const JPEG_QUALITY: u8 = 85;
+
+/// Box a rendition is scaled down to fit into, keeping the aspect ratio of the original.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenditionSize {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RenditionSize {
+    /// Sizes made for every uploaded image, for lists and detail pages of dashboards.
+    pub fn standard() -> Vec<RenditionSize> {
+        vec![
+            RenditionSize {
+                name: "thumbnail".to_string(),
+                width: 160,
+                height: 160,
+            },
+            RenditionSize {
+                name: "preview".to_string(),
+                width: 640,
+                height: 640,
+            },
+        ]
+    }
+
+    /// Size matching a screen's native resolution, named after it (e.g. `1920x1080`).
+    pub fn for_screen(resolution: &Resolution) -> Option<RenditionSize> {
+        let width = u32::try_from(resolution.width).ok().filter(|&w| w > 0)?;
+        let height = u32::try_from(resolution.height).ok().filter(|&h| h > 0)?;
+        Some(RenditionSize {
+            name: format!("{}x{}", width, height),
+            width,
+            height,
+        })
+    }
+}
+
+/// Resized copy of an uploaded image, written to a temporary file until it is stored.
+pub struct Rendition {
+    pub name: String,
+    pub media_id: String,
+    pub format: MediaFormat,
+    pub width: u32,
+    pub height: u32,
+    pub path: PathBuf,
+}
+
+/// Decodes the image at `source` and writes a scaled down copy for every size it is larger than.
+/// Images are never scaled up, so a size the original already fits into is skipped and clients
+/// fall back to the original. JPEG photos stay JPEG, everything else is written as PNG to keep
+/// transparency. Animated images only keep their first frame.
+pub fn render(
+    source: &Path,
+    media_id: &str,
+    format: MediaFormat,
+    sizes: &[RenditionSize],
+) -> Result<Vec<Rendition>, AppError> {
+    let image = decode(source, format)?;
+    let (width, height) = image.dimensions();
+    let output_format = match format {
+        MediaFormat::Jpeg => MediaFormat::Jpeg,
+        _ => MediaFormat::Png,
+    };
+
+    let mut renditions: Vec<Rendition> = Vec::new();
+    for size in sizes {
+        if width <= size.width && height <= size.height {
+            continue;
+        }
+
+        let resized = image.resize(size.width, size.height, FilterType::CatmullRom);
+        let rendition_id = rendition_media_id(media_id, &size.name, output_format);
+        let path = staged_upload_path(&rendition_id);
+        let rendition = Rendition {
+            name: size.name.clone(),
+            media_id: rendition_id,
+            format: output_format,
+            width: resized.width(),
+            height: resized.height(),
+            path,
+        };
+
+        // Keep the files written so far in the list, so the caller can clean them all up.
+        let written = encode(&resized, output_format, &rendition.path);
+        renditions.push(rendition);
+        if let Err(err) = written {
+            remove_renditions(&renditions);
+            return Err(err);
+        }
+    }
+
+    Ok(renditions)
+}
+
+/// Deletes the temporary files of renditions that were not handed to the media store.
+pub fn remove_renditions(renditions: &[Rendition]) {
+    for rendition in renditions {
+        let _ = std::fs::remove_file(&rendition.path);
+    }
+}
+
+fn decode(source: &Path, format: MediaFormat) -> Result<DynamicImage, AppError> {
+    let image_format = match format {
+        MediaFormat::Png => ImageFormat::Png,
+        MediaFormat::Jpeg => ImageFormat::Jpeg,
+        MediaFormat::WebP => ImageFormat::WebP,
+        MediaFormat::Gif => ImageFormat::Gif,
+        MediaFormat::Mp4 | MediaFormat::WebM => {
+            return Err(AppError::new(
+                None,
+                Some(format!("{} has no renditions", format.content_type())),
+                AppErrorType::SomethingWentWrong,
+            ))
+        }
+    };
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DECODED_SIDE);
+    limits.max_image_height = Some(MAX_DECODED_SIDE);
+
+    let mut reader = Reader::open(source)?;
+    reader.set_format(image_format);
+    reader.limits(limits);
+    reader.decode().map_err(|err| {
+        AppError::new(
+            Some(format!(
+                "{} image could not be decoded",
+                format.content_type()
+            )),
+            Some(err.to_string()),
+            AppErrorType::UnsupportedMediaError,
+        )
+    })
+}
+
+fn encode(image: &DynamicImage, format: MediaFormat, path: &Path) -> Result<(), AppError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let result = match format {
+        MediaFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_to(&mut writer, ImageOutputFormat::Jpeg(JPEG_QUALITY)),
+        _ => image.write_to(&mut writer, ImageOutputFormat::Png),
+    };
+
+    result.map_err(|err| {
+        AppError::new(
+            None,
+            Some(err.to_string()),
+            AppErrorType::SomethingWentWrong,
+        )
+    })
+}
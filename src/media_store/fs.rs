@@ -0,0 +1,88 @@
+use crate::errors::AppError;
+use crate::media_store::{storage_error, MediaLocation, MediaStore, StoredMedia};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Keeps media in a directory on the server's own disk.
+pub struct FsMediaStore {
+    root: PathBuf,
+}
+
+impl FsMediaStore {
+    pub fn new(root: PathBuf) -> Self {
+        FsMediaStore { root }
+    }
+
+    fn find(&self, media_id: &str) -> Option<PathBuf> {
+        let file_path = self.root.join(media_id);
+        if file_path.exists() {
+            return Some(file_path);
+        }
+
+        // Older uploads were saved with percent-encoded dashes in their names.
+        let legacy_path = self.root.join(media_id.replace('-', "%2D"));
+        if legacy_path.exists() {
+            Some(legacy_path)
+        } else {
+            None
+        }
+    }
+}
+
+impl MediaStore for FsMediaStore {
+    fn put(&self, media_id: &str, _content_type: &str, source: &Path) -> Result<(), AppError> {
+        let file_path = self.root.join(media_id);
+
+        // Uploads are staged in the temp directory, which may be on another file system.
+        if std::fs::rename(source, &file_path).is_err() {
+            std::fs::copy(source, &file_path).map_err(|err| storage_error(err.to_string()))?;
+            let _ = std::fs::remove_file(source);
+        }
+        Ok(())
+    }
+
+    fn exists(&self, media_id: &str) -> Result<bool, AppError> {
+        Ok(self.find(media_id).is_some())
+    }
+
+    fn locate(&self, media_id: &str) -> Result<Option<MediaLocation>, AppError> {
+        Ok(self.find(media_id).map(MediaLocation::File))
+    }
+
+    /// Hidden files such as `.gitkeep` and subdirectories are not media and are left out.
+    fn list(&self) -> Result<Vec<StoredMedia>, AppError> {
+        let entries =
+            std::fs::read_dir(&self.root).map_err(|err| storage_error(err.to_string()))?;
+
+        let mut stored_media: Vec<StoredMedia> = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|err| storage_error(err.to_string()))?;
+            let key = match entry.file_name().into_string() {
+                Ok(key) if !key.starts_with('.') => key,
+                _ => continue,
+            };
+            let metadata = entry
+                .metadata()
+                .map_err(|err| storage_error(err.to_string()))?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            stored_media.push(StoredMedia {
+                key,
+                size_bytes: metadata.len(),
+                modified_at: metadata
+                    .modified()
+                    .map_err(|err| storage_error(err.to_string()))?,
+            });
+        }
+        Ok(stored_media)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), AppError> {
+        match std::fs::remove_file(self.root.join(key)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(storage_error(err.to_string())),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::actors::db::DbActor;
+use crate::actors::media::{ForgetMediaRenditions, GetReferencedMedia};
+use crate::config::MediaGcConfig;
+use crate::db_utils::pg_timestamp;
+use crate::errors::{AppError, AppErrorType};
+use crate::media::{canonical_media_id, STAGED_UPLOAD_PREFIX};
+use crate::media_store::MediaStore;
+use crate::models::media::{MediaGcReport, OrphanedMedia};
+use actix::Addr;
+use actix_web::web;
+use slog::{warn, Logger};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Reconciles the media store against the images users, businesses and ads refer to and deletes
+/// unreferenced media, including renditions of it, once it is older than the grace period.
+/// Staged files of uploads that never reached the store, such as multipart uploads abandoned by
+/// the client, are cleaned up the same way. A dry run deletes nothing and reports what would go.
+pub async fn collect_media_garbage(
+    db: &Addr<DbActor>,
+    media_store: Arc<dyn MediaStore>,
+    config: MediaGcConfig,
+    dry_run: bool,
+    logger: &Logger,
+) -> Result<MediaGcReport, AppError> {
+    let referenced = match db
+        .send(GetReferencedMedia {
+            logger: logger.clone(),
+        })
+        .await
+    {
+        Ok(res) => res?,
+        Err(err) => return Err(AppError::from_mailbox(err)),
+    };
+
+    let grace_period = config.grace_period();
+    let sweep_logger = logger.clone();
+    let (mut report, deleted) = web::block(move || {
+        sweep(
+            media_store.as_ref(),
+            &referenced,
+            grace_period,
+            dry_run,
+            &sweep_logger,
+        )
+    })
+    .await
+    .map_err(|err| {
+        AppError::new(
+            None,
+            Some(err.to_string()),
+            AppErrorType::SomethingWentWrong,
+        )
+    })??;
+    report.grace_hours = config.grace_hours;
+
+    if !deleted.is_empty() {
+        match db
+            .send(ForgetMediaRenditions {
+                media_ids: deleted,
+                logger: logger.clone(),
+            })
+            .await
+        {
+            Ok(res) => res?,
+            Err(err) => return Err(AppError::from_mailbox(err)),
+        };
+    }
+
+    Ok(report)
+}
+
+/// Returns the report and the canonical ids of the media that was deleted.
+fn sweep(
+    media_store: &dyn MediaStore,
+    referenced: &HashSet<String>,
+    grace_period: Duration,
+    dry_run: bool,
+    logger: &Logger,
+) -> Result<(MediaGcReport, Vec<String>), AppError> {
+    let cutoff = SystemTime::now()
+        .checked_sub(grace_period)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let stored_media = media_store.list()?;
+
+    let mut report = MediaGcReport {
+        dry_run,
+        grace_hours: 0,
+        scanned: stored_media.len(),
+        referenced: 0,
+        in_grace_period: 0,
+        unrecognized: vec![],
+        orphans: vec![],
+        orphaned_bytes: 0,
+        abandoned_uploads: vec![],
+        failed: vec![],
+    };
+    let mut deleted: Vec<String> = Vec::new();
+
+    for media in stored_media {
+        let media_id = match canonical_media_id(&media.key) {
+            Some(media_id) => media_id,
+            None => {
+                report.unrecognized.push(media.key);
+                continue;
+            }
+        };
+        if referenced.contains(&media_id) {
+            report.referenced += 1;
+            continue;
+        }
+        if media.modified_at > cutoff {
+            report.in_grace_period += 1;
+            continue;
+        }
+
+        if !dry_run {
+            if let Err(err) = media_store.delete(&media.key) {
+                warn!(
+                    logger,
+                    "Failed to delete orphaned media {}: {}", media.key, err
+                );
+                report.failed.push(media.key);
+                continue;
+            }
+            deleted.push(media_id);
+        }
+
+        report.orphaned_bytes += media.size_bytes;
+        report.orphans.push(OrphanedMedia {
+            key: media.key,
+            size_bytes: media.size_bytes,
+            modified_at: pg_timestamp(media.modified_at).0,
+        });
+    }
+
+    report.abandoned_uploads = sweep_staged_uploads(cutoff, dry_run, &mut report.failed, logger);
+    Ok((report, deleted))
+}
+
+/// Removes staged upload files last modified before `cutoff` and returns their names.
+fn sweep_staged_uploads(
+    cutoff: SystemTime,
+    dry_run: bool,
+    failed: &mut Vec<String>,
+    logger: &Logger,
+) -> Vec<String> {
+    let entries = match std::fs::read_dir(std::env::temp_dir()) {
+        Ok(entries) => entries,
+        Err(err) => {
+            warn!(logger, "Failed to list staged uploads: {}", err);
+            return vec![];
+        }
+    };
+
+    let mut abandoned_uploads: Vec<String> = Vec::new();
+    for entry in entries.flatten() {
+        let name = match entry.file_name().into_string() {
+            Ok(name) if name.starts_with(STAGED_UPLOAD_PREFIX) => name,
+            _ => continue,
+        };
+        let is_abandoned = entry.metadata().is_ok_and(|metadata| {
+            metadata.is_file() && metadata.modified().is_ok_and(|modified| modified <= cutoff)
+        });
+        if !is_abandoned {
+            continue;
+        }
+
+        if !dry_run {
+            if let Err(err) = std::fs::remove_file(entry.path()) {
+                warn!(logger, "Failed to delete staged upload {}: {}", name, err);
+                failed.push(name);
+                continue;
+            }
+        }
+        abandoned_uploads.push(name);
+    }
+    abandoned_uploads
+}
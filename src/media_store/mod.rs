@@ -0,0 +1,52 @@
+use crate::errors::{AppError, AppErrorType};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub mod fs;
+pub mod gc;
+pub mod s3;
+
+/// Where stored media can be downloaded from.
+pub enum MediaLocation {
+    /// File on this server's disk, served by the application itself.
+    File(PathBuf),
+    /// Presigned URL the client is redirected to.
+    Url(String),
+}
+
+/// Media found in the store by `MediaStore::list`.
+pub struct StoredMedia {
+    /// Name the media is stored under. Files saved by older versions may use a different
+    /// spelling than their canonical media id.
+    pub key: String,
+    pub size_bytes: u64,
+    pub modified_at: SystemTime,
+}
+
+/// Storage behind uploaded media. Media is addressed by its canonical media id
+/// (`<uuid>.<extension>`, see `media::parse_media_id`).
+///
+/// Calls may block on disk or network IO, so async handlers run them with `web::block`.
+pub trait MediaStore: Send + Sync {
+    /// Takes over a fully written upload from `source` and stores it under `media_id`.
+    fn put(&self, media_id: &str, content_type: &str, source: &Path) -> Result<(), AppError>;
+
+    fn exists(&self, media_id: &str) -> Result<bool, AppError>;
+
+    /// Returns where the media can be downloaded from, or `None` when it is known not to exist.
+    fn locate(&self, media_id: &str) -> Result<Option<MediaLocation>, AppError>;
+
+    /// Lists everything in the store, for garbage collection.
+    fn list(&self) -> Result<Vec<StoredMedia>, AppError>;
+
+    /// Deletes media by the key it is listed under. Deleting missing media is not an error.
+    fn delete(&self, key: &str) -> Result<(), AppError>;
+}
+
+fn storage_error(cause: String) -> AppError {
+    AppError::new(
+        Some("Media storage is unavailable".to_string()),
+        Some(cause),
+        AppErrorType::SomethingWentWrong,
+    )
+}
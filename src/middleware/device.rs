@@ -0,0 +1,57 @@
+use crate::actors::device::AuthorizeDevice;
+use crate::models::app_state::AppState;
+use crate::models::device::DeviceClaims;
+use actix_web::web::Data;
+use actix_web::{dev::ServiceRequest, error::Error, HttpMessage};
+use actix_web_httpauth::extractors::{
+    bearer::{self, BearerAuth},
+    AuthenticationError,
+};
+use slog::error;
+
+/// Authenticates screen players by the API key issued for their screen.
+pub async fn device_validator(
+    req: ServiceRequest,
+    credentials: BearerAuth,
+) -> Result<ServiceRequest, (Error, ServiceRequest)> {
+    let screen_id = match req.app_data::<Data<AppState>>() {
+        Some(state) => {
+            let result = state
+                .db
+                .send(AuthorizeDevice {
+                    api_key: credentials.token().to_string(),
+                    logger: state.logger.clone(),
+                })
+                .await;
+
+            match result {
+                Ok(Ok(screen_id)) => screen_id,
+                Ok(Err(err)) => {
+                    error!(state.logger, "Failed to authorize device: {}", err);
+                    None
+                }
+                Err(err) => {
+                    error!(state.logger, "Failed to authorize device: {}", err);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    match screen_id {
+        Some(screen_id) => {
+            req.extensions_mut().insert(DeviceClaims { screen_id });
+            Ok(req)
+        }
+        None => {
+            let config = req
+                .app_data::<bearer::Config>()
+                .cloned()
+                .unwrap_or_default()
+                .scope("");
+
+            Err((AuthenticationError::from(config).into(), req))
+        }
+    }
+}
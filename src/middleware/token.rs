@@ -123,12 +123,10 @@ pub fn authorize(
 fn get_password(basic_auth: BasicAuth) -> Result<String, AppError> {
     match basic_auth.password() {
         Some(pass) => Ok(pass.to_string()),
-        None => {
-            return Err(AppError {
-                message: Some("Must provide username and password".to_string()),
-                cause: None,
-                error_type: AppErrorType::PasswordOrLoginError,
-            })
-        }
+        None => Err(AppError {
+            message: Some("Must provide username and password".to_string()),
+            cause: None,
+            error_type: AppErrorType::PasswordOrLoginError,
+        }),
     }
 }
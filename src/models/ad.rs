@@ -1,6 +1,8 @@
+use crate::models::media::Renditions;
 use crate::models::user::User;
 use diesel::{Associations, Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use uuid::Uuid;
 
 use crate::schema::ads;
@@ -16,13 +18,12 @@ pub struct Ad {
     pub user_id: Uuid,
 }
 
+/// Ad as returned to clients, with the renditions of its image.
 #[derive(Serialize, Deserialize)]
-pub struct AdAllData {
-    pub ad_id: Uuid,
-    pub ad_name: String,
-    pub img_url: String,
-    pub status: String,
-    pub user: User,
+pub struct AdInfo {
+    #[serde(flatten)]
+    pub ad: Ad,
+    pub img_renditions: Renditions,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -37,7 +38,9 @@ pub struct AdDataUpdate {
     pub ad_id: Uuid,
     pub ad_name: String,
     pub img_url: String,
-    pub user_id: Uuid,
+    /// Lets an admin edit an ad that belongs to another user.
+    #[serde(default)]
+    pub admin_override: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -53,12 +56,12 @@ pub enum AdStatus {
     Rejected,
 }
 
-impl ToString for AdStatus {
-    fn to_string(&self) -> String {
+impl fmt::Display for AdStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            AdStatus::Unverified => "Unverified".to_string(),
-            AdStatus::Approved => "Approved".to_string(),
-            AdStatus::Rejected => "Rejected".to_string(),
+            AdStatus::Unverified => write!(f, "Unverified"),
+            AdStatus::Approved => write!(f, "Approved"),
+            AdStatus::Rejected => write!(f, "Rejected"),
         }
     }
 }
@@ -1,9 +1,13 @@
-use crate::models::ad::Ad;
+use crate::models::ad::AdInfo;
 use crate::models::screen::Screen;
-use crate::models::user::User;
+use crate::models::user::UserInfo;
+use chrono::Weekday;
+use chrono_tz::Tz;
 use diesel::data_types::PgTimestamp;
 use diesel::{Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
 use crate::schema::ad_orders;
@@ -15,18 +19,122 @@ pub struct AdOrder {
     pub start_time: PgTimestamp,
     pub end_time: PgTimestamp,
     pub price: f64,
-    pub is_rejected: bool,
     pub ad_id: Uuid,
     pub screen_id: Uuid,
+    pub status: String,
+    pub campaign_id: Option<Uuid>,
+    /// Set when the order's screen stopped sending heartbeats while the order was running.
+    pub offline_flagged_at: Option<PgTimestamp>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct AdOrderData {
     pub start_time: i64,
     pub end_time: i64,
-    pub price: f64,
+    /// Price the client expects to pay; checked against the server quote when present.
+    pub price: Option<f64>,
     pub ad_id: Uuid,
     pub screen_id: Uuid,
+    /// Lets an admin book with an ad that belongs to another user.
+    #[serde(default)]
+    pub admin_override: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum RecurrenceFrequency {
+    Daily,
+    Weekly,
+}
+
+/// Repeats an order's window at the same wall-clock time, with days counted in `timezone`.
+#[derive(Serialize, Deserialize)]
+pub struct RecurrenceRule {
+    pub frequency: RecurrenceFrequency,
+    /// IANA name of the zone the days are counted in, such as `Europe/Kyiv`; UTC when left out.
+    #[serde(default = "default_timezone")]
+    pub timezone: Tz,
+    /// Days a weekly rule runs on; the first order's weekday when empty.
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+    /// No occurrence starts at or after this time.
+    pub until: i64,
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecurringAdOrderData {
+    /// Window of the first occurrence.
+    pub start_time: i64,
+    pub end_time: i64,
+    pub recurrence: RecurrenceRule,
+    /// Total the client expects to pay for the booked occurrences; checked when present.
+    pub price: Option<f64>,
+    pub ad_id: Uuid,
+    pub screen_id: Uuid,
+    /// Books the free occurrences instead of failing when some are taken.
+    #[serde(default)]
+    pub skip_unavailable: bool,
+    #[serde(default)]
+    pub admin_override: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecurrencePreviewData {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub recurrence: RecurrenceRule,
+    pub screen_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecurrenceOccurrence {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub price: f64,
+    pub is_available: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecurrencePreview {
+    pub screen_id: Uuid,
+    pub occurrences: Vec<RecurrenceOccurrence>,
+    pub available_count: usize,
+    /// Price of the available occurrences.
+    pub available_price: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecurringAdOrders {
+    pub order_ids: Vec<Uuid>,
+    pub total_price: f64,
+    pub skipped: Vec<RecurrenceOccurrence>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdOrderDecision {
+    pub order_id: Uuid,
+    /// Lets an admin approve or reject an order on behalf of the screen's business.
+    #[serde(default)]
+    pub admin_override: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdOrderQuoteData {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub screen_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdOrderQuote {
+    pub screen_id: Uuid,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub billed_units: i64,
+    pub price: f64,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -40,9 +148,98 @@ pub struct AdOrderAllData {
     pub start_time: i64,
     pub end_time: i64,
     pub price: f64,
-    pub is_rejected: bool,
+    pub status: String,
+    pub is_paid: bool,
+    pub offline_flagged_at: Option<i64>,
     pub address_name: String,
-    pub ad: Ad,
-    pub client: User,
+    pub ad: AdInfo,
+    pub client: UserInfo,
     pub screen: Screen,
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct UserAdOrderData {
+    pub order_id: Uuid,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub price: f64,
+    pub status: String,
+    pub is_paid: bool,
+    pub offline_flagged_at: Option<i64>,
+    pub address_name: String,
+    pub ad: AdInfo,
+    pub screen: Screen,
+}
+
+/// Filters for listing ad orders. `status` is a comma separated list of statuses and
+/// `from`/`to` keep the orders whose window overlaps the range.
+#[derive(Serialize, Deserialize)]
+pub struct AdOrderFilter {
+    pub status: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AdOrderStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Cancelled,
+    Running,
+    Completed,
+}
+
+impl AdOrderStatus {
+    /// Statuses of an order that holds its screen time.
+    pub fn holding() -> Vec<String> {
+        vec![
+            AdOrderStatus::Approved.to_string(),
+            AdOrderStatus::Running.to_string(),
+        ]
+    }
+
+    pub fn can_transition_to(&self, next: AdOrderStatus) -> bool {
+        use AdOrderStatus::*;
+
+        matches!(
+            (self, next),
+            (Pending, Approved)
+                | (Pending, Rejected)
+                | (Pending, Cancelled)
+                | (Approved, Rejected)
+                | (Approved, Cancelled)
+                | (Approved, Running)
+                | (Running, Completed)
+        )
+    }
+}
+
+impl fmt::Display for AdOrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdOrderStatus::Pending => write!(f, "Pending"),
+            AdOrderStatus::Approved => write!(f, "Approved"),
+            AdOrderStatus::Rejected => write!(f, "Rejected"),
+            AdOrderStatus::Cancelled => write!(f, "Cancelled"),
+            AdOrderStatus::Running => write!(f, "Running"),
+            AdOrderStatus::Completed => write!(f, "Completed"),
+        }
+    }
+}
+
+impl FromStr for AdOrderStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "Pending" => Ok(AdOrderStatus::Pending),
+            "Approved" => Ok(AdOrderStatus::Approved),
+            "Rejected" => Ok(AdOrderStatus::Rejected),
+            "Cancelled" => Ok(AdOrderStatus::Cancelled),
+            "Running" => Ok(AdOrderStatus::Running),
+            "Completed" => Ok(AdOrderStatus::Completed),
+            _ => Err(format!("Unknown ad order status: {}", status)),
+        }
+    }
+}
@@ -12,10 +12,18 @@ pub struct Address {
     pub address_id: Uuid,
     pub address_name: String,
     pub business_id: Uuid,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub city: Option<String>,
+    pub region: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct AddressData {
     pub address_name: String,
     pub business_id: Uuid,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub city: Option<String>,
+    pub region: Option<String>,
 }
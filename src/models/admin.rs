@@ -1,5 +1,5 @@
 use diesel::{Insertable, Queryable};
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use uuid::Uuid;
 
 use crate::schema::admin;
@@ -11,9 +11,3 @@ pub struct Admin {
     pub admin_name: String,
     pub password: String,
 }
-
-#[derive(Serialize, Deserialize)]
-pub struct AdminData {
-    pub admin_name: String,
-    pub password: String,
-}
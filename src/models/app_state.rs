@@ -1,8 +1,17 @@
 use crate::actors::db::DbActor;
+use crate::config::{BookingConfig, MediaGcConfig, ScreenConfig};
+use crate::media_store::MediaStore;
+use crate::payment_provider::PaymentProvider;
 use actix::Addr;
 use slog::Logger;
+use std::sync::Arc;
 
 pub struct AppState {
     pub db: Addr<DbActor>,
     pub logger: Logger,
+    pub payment_provider: Arc<dyn PaymentProvider>,
+    pub media_store: Arc<dyn MediaStore>,
+    pub booking: BookingConfig,
+    pub screens: ScreenConfig,
+    pub media_gc: MediaGcConfig,
 }
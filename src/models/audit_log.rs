@@ -0,0 +1,55 @@
+use diesel::data_types::PgTimestamp;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::schema::audit_logs;
+
+/// Record of an admin acting on a resource owned by someone else.
+#[derive(Debug, Clone, Queryable, Insertable, Selectable)]
+#[diesel(table_name = audit_logs)]
+pub struct AuditLog {
+    pub audit_log_id: Uuid,
+    pub admin_id: Uuid,
+    pub action: String,
+    pub target_id: Uuid,
+    pub owner_id: Uuid,
+    pub created_at: PgTimestamp,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AuditLogData {
+    pub audit_log_id: Uuid,
+    pub admin_id: Uuid,
+    pub action: String,
+    pub target_id: Uuid,
+    pub owner_id: Uuid,
+    pub created_at: i64,
+}
+
+/// Page of the audit log, newest entries first.
+#[derive(Serialize, Deserialize)]
+pub struct AuditLogQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum AuditAction {
+    CreateAdOrder,
+    ApproveAdOrder,
+    RejectAdOrder,
+    UpdateAd,
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditAction::CreateAdOrder => write!(f, "CreateAdOrder"),
+            AuditAction::ApproveAdOrder => write!(f, "ApproveAdOrder"),
+            AuditAction::RejectAdOrder => write!(f, "RejectAdOrder"),
+            AuditAction::UpdateAd => write!(f, "UpdateAd"),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::models::category::Category;
+use crate::models::media::Renditions;
 use crate::models::screen::Screen;
 use diesel::{AsChangeset, Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
@@ -17,6 +18,14 @@ pub struct Business {
     pub img_url: String,
 }
 
+/// Business as listed to clients, with the renditions of its image.
+#[derive(Serialize)]
+pub struct BusinessListing {
+    #[serde(flatten)]
+    pub business: Business,
+    pub img_renditions: Renditions,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct BusinessData {
     pub business_name: String,
@@ -33,4 +42,9 @@ pub struct BusinessInfo {
     pub email: String,
     pub categories: Vec<Category>,
     pub screens: Vec<Screen>,
+    /// Read only, the image is changed through `/change_img`.
+    #[serde(default)]
+    pub img_url: String,
+    #[serde(default)]
+    pub img_renditions: Renditions,
 }
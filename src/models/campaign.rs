@@ -0,0 +1,113 @@
+use crate::models::ad_order::AdOrderStatus;
+use diesel::data_types::PgTimestamp;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::schema::campaigns;
+
+/// Group of ad orders for one ad, booked together and paid with one authorization.
+#[derive(Debug, Clone, Queryable, Insertable, Selectable)]
+#[diesel(table_name = campaigns)]
+pub struct Campaign {
+    pub campaign_id: Uuid,
+    pub campaign_name: String,
+    pub user_id: Uuid,
+    pub ad_id: Uuid,
+    pub budget: f64,
+    pub total_price: f64,
+    pub provider_reference: String,
+    pub created_at: PgTimestamp,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CampaignSlot {
+    pub screen_id: Uuid,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CampaignData {
+    pub campaign_name: String,
+    pub ad_id: Uuid,
+    pub budget: f64,
+    pub slots: Vec<CampaignSlot>,
+    /// Total the client expects to pay; checked against the server quotes when present.
+    pub price: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CampaignOrder {
+    pub order_id: Uuid,
+    pub screen_id: Uuid,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub price: f64,
+    pub status: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CampaignInfo {
+    pub campaign_id: Uuid,
+    pub campaign_name: String,
+    pub ad_id: Uuid,
+    pub budget: f64,
+    /// Price of every order booked with the campaign.
+    pub total_price: f64,
+    /// Price of the orders that have not been rejected or cancelled.
+    pub active_price: f64,
+    pub status: String,
+    pub created_at: i64,
+    pub orders: Vec<CampaignOrder>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum CampaignStatus {
+    Pending,
+    Active,
+    Completed,
+    Cancelled,
+}
+
+impl CampaignStatus {
+    /// Sums up the statuses of the campaign's orders. Rejected and cancelled orders are left
+    /// out unless nothing else is left.
+    pub fn from_orders(statuses: &[String]) -> CampaignStatus {
+        let live: Vec<&String> = statuses
+            .iter()
+            .filter(|status| {
+                **status != AdOrderStatus::Rejected.to_string()
+                    && **status != AdOrderStatus::Cancelled.to_string()
+            })
+            .collect();
+
+        if live.is_empty() {
+            CampaignStatus::Cancelled
+        } else if live
+            .iter()
+            .all(|status| **status == AdOrderStatus::Completed.to_string())
+        {
+            CampaignStatus::Completed
+        } else if live
+            .iter()
+            .all(|status| **status == AdOrderStatus::Pending.to_string())
+        {
+            CampaignStatus::Pending
+        } else {
+            CampaignStatus::Active
+        }
+    }
+}
+
+impl fmt::Display for CampaignStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CampaignStatus::Pending => write!(f, "Pending"),
+            CampaignStatus::Active => write!(f, "Active"),
+            CampaignStatus::Completed => write!(f, "Completed"),
+            CampaignStatus::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
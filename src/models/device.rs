@@ -0,0 +1,105 @@
+use diesel::data_types::PgTimestamp;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+use crate::schema::screen_devices;
+
+/// API key of the player installed on a screen. Only the SHA-256 of the key is stored.
+#[derive(Debug, Clone, Queryable, Insertable, Selectable)]
+#[diesel(table_name = screen_devices)]
+pub struct ScreenDevice {
+    pub screen_id: Uuid,
+    pub api_key_hash: String,
+    pub created_at: PgTimestamp,
+    pub last_seen_at: Option<PgTimestamp>,
+    pub software_version: Option<String>,
+    pub free_storage_bytes: Option<i64>,
+}
+
+impl ScreenDevice {
+    /// A device that has not sent a heartbeat yet is given the threshold from when its key was
+    /// issued, so a freshly installed player is not reported offline straight away.
+    pub fn online_status(&self, now: PgTimestamp, offline_after_micros: i64) -> ScreenOnlineStatus {
+        let last_seen_at = self.last_seen_at.unwrap_or(self.created_at);
+        if now.0 - last_seen_at.0 <= offline_after_micros {
+            ScreenOnlineStatus::Online
+        } else {
+            ScreenOnlineStatus::Offline
+        }
+    }
+}
+
+/// Returned once when a key is issued; it can't be read back afterwards.
+#[derive(Serialize, Deserialize)]
+pub struct DeviceKey {
+    pub screen_id: Uuid,
+    pub api_key: String,
+}
+
+/// Identity of an authenticated device, stored in the request extensions.
+#[derive(Clone)]
+pub struct DeviceClaims {
+    pub screen_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlaylistQuery {
+    pub hours: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlaylistItem {
+    pub order_id: Uuid,
+    pub ad_id: Uuid,
+    pub ad_name: String,
+    /// The rendition made for the screen's resolution when there is one, otherwise the original.
+    pub media_id: String,
+    pub media_url: String,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Playlist {
+    pub screen_id: Uuid,
+    pub items: Vec<PlaylistItem>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HeartbeatData {
+    pub software_version: String,
+    pub free_storage_bytes: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ScreenOnlineStatus {
+    Online,
+    Offline,
+    /// No device key has been issued for the screen, so it isn't monitored.
+    Unregistered,
+}
+
+impl fmt::Display for ScreenOnlineStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenOnlineStatus::Online => write!(f, "Online"),
+            ScreenOnlineStatus::Offline => write!(f, "Offline"),
+            ScreenOnlineStatus::Unregistered => write!(f, "Unregistered"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScreenHealth {
+    pub screen_id: Uuid,
+    pub screen_name: String,
+    pub status: String,
+    pub online_status: String,
+    pub last_seen_at: Option<i64>,
+    pub software_version: Option<String>,
+    pub free_storage_bytes: Option<i64>,
+    /// Orders that were running while the screen was offline.
+    pub flagged_order_ids: Vec<Uuid>,
+}
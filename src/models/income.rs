@@ -1,6 +1,6 @@
-use crate::models::ad::Ad;
+use crate::models::ad::AdInfo;
 use crate::models::ad_order::AdOrder;
-use crate::models::user::User;
+use crate::models::user::UserInfo;
 use diesel::{Associations, Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -20,6 +20,6 @@ pub struct Income {
 #[derive(Serialize, Deserialize)]
 pub struct IncomeAllData {
     pub price: f64,
-    pub client: User,
-    pub ad: Ad,
+    pub client: UserInfo,
+    pub ad: AdInfo,
 }
@@ -0,0 +1,73 @@
+use crate::models::screen_characteristics::MediaType;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::schema::media_renditions;
+
+/// Urls of an image's resized renditions, keyed by rendition name (`thumbnail`, `preview` or a
+/// screen resolution such as `1920x1080`). A missing rendition means the original is already
+/// small enough to be used in its place.
+pub type Renditions = BTreeMap<String, String>;
+
+/// Url that stored media is served from.
+pub fn media_url(media_id: &str) -> String {
+    format!("/images/{}", media_id)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UploadedMedia {
+    /// Served from `/images/{media_id}`.
+    pub media_id: String,
+    pub content_type: String,
+    pub media_type: MediaType,
+    pub size_bytes: u64,
+    pub renditions: Renditions,
+}
+
+#[derive(Debug, Clone, Queryable, Insertable, Selectable)]
+#[diesel(table_name = media_renditions)]
+pub struct MediaRendition {
+    /// Media id of the original upload.
+    pub media_id: String,
+    pub rendition: String,
+    pub rendition_media_id: String,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Image of a user or business, returned after it is changed.
+#[derive(Serialize, Deserialize)]
+pub struct ImageInfo {
+    pub img_url: String,
+    pub img_renditions: Renditions,
+}
+
+/// Stored media that nothing refers to.
+#[derive(Serialize, Deserialize)]
+pub struct OrphanedMedia {
+    /// Name the media is stored under.
+    pub key: String,
+    pub size_bytes: u64,
+    pub modified_at: i64,
+}
+
+/// Outcome of a garbage collection run over stored media.
+#[derive(Serialize, Deserialize)]
+pub struct MediaGcReport {
+    /// Nothing was deleted, `orphans` lists what would have been.
+    pub dry_run: bool,
+    pub grace_hours: u64,
+    pub scanned: usize,
+    pub referenced: usize,
+    /// Unreferenced media kept because it is younger than the grace period.
+    pub in_grace_period: usize,
+    /// Files in the store that are not named like media, which are never deleted.
+    pub unrecognized: Vec<String>,
+    pub orphans: Vec<OrphanedMedia>,
+    pub orphaned_bytes: u64,
+    /// Staged files of uploads that were abandoned before reaching the store.
+    pub abandoned_uploads: Vec<String>,
+    /// Orphans and abandoned uploads that could not be deleted.
+    pub failed: Vec<String>,
+}
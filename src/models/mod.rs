@@ -3,9 +3,15 @@ pub mod ad_order;
 pub mod address;
 pub mod admin;
 pub mod app_state;
+pub mod audit_log;
 pub mod business;
+pub mod campaign;
 pub mod category;
+pub mod device;
 pub mod income;
+pub mod media;
 pub mod payment;
+pub mod play_event;
 pub mod screen;
+pub mod screen_characteristics;
 pub mod user;
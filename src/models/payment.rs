@@ -1,21 +1,109 @@
-use diesel::{Insertable, Queryable};
+use diesel::{Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
 use crate::schema::payments;
 
-#[derive(Debug, Clone, Queryable, Insertable)]
+/// Separates the provider's authorization from the part that tells apart the payments sharing it.
+const REFERENCE_SEPARATOR: char = '/';
+
+#[derive(Debug, Clone, Serialize, Queryable, Insertable, Selectable)]
 #[diesel(table_name = payments)]
 pub struct Payment {
     pub payment_id: Uuid,
     pub price: f64,
     pub user_id: Uuid,
     pub ad_order_id: Uuid,
+    pub status: String,
+    pub provider_reference: String,
+}
+
+impl Payment {
+    /// Reference of the authorization at the provider that this payment takes its money from.
+    pub fn provider_transaction(&self) -> &str {
+        self.provider_reference
+            .split(REFERENCE_SEPARATOR)
+            .next()
+            .unwrap_or_default()
+    }
+}
+
+/// Reference of an order's payment on an authorization shared by several orders, so webhook
+/// events for one of them leave the others alone.
+pub fn shared_payment_reference(provider_transaction: &str, ad_order_id: Uuid) -> String {
+    format!(
+        "{}{}{}",
+        provider_transaction, REFERENCE_SEPARATOR, ad_order_id
+    )
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct PaymentData {
     pub price: f64,
-    pub user_id: Uuid,
     pub order_id: Uuid,
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct PaymentId {
+    pub payment_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PaymentWebhookEvent {
+    pub provider_reference: String,
+    pub status: PaymentStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaymentStatus {
+    Authorized,
+    Captured,
+    Voided,
+    Refunded,
+}
+
+impl PaymentStatus {
+    /// Statuses of a payment that still covers its ad order.
+    pub fn active() -> Vec<String> {
+        vec![
+            PaymentStatus::Authorized.to_string(),
+            PaymentStatus::Captured.to_string(),
+        ]
+    }
+
+    pub fn can_transition_to(&self, next: PaymentStatus) -> bool {
+        use PaymentStatus::*;
+
+        matches!(
+            (self, next),
+            (Authorized, Captured) | (Authorized, Voided) | (Captured, Refunded)
+        )
+    }
+}
+
+impl fmt::Display for PaymentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentStatus::Authorized => write!(f, "Authorized"),
+            PaymentStatus::Captured => write!(f, "Captured"),
+            PaymentStatus::Voided => write!(f, "Voided"),
+            PaymentStatus::Refunded => write!(f, "Refunded"),
+        }
+    }
+}
+
+impl FromStr for PaymentStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "Authorized" => Ok(PaymentStatus::Authorized),
+            "Captured" => Ok(PaymentStatus::Captured),
+            "Voided" => Ok(PaymentStatus::Voided),
+            "Refunded" => Ok(PaymentStatus::Refunded),
+            _ => Err(format!("Unknown payment status: {}", status)),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use diesel::data_types::PgTimestamp;
+use diesel::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::schema::play_events;
+
+/// Proof that a screen played an ad order.
+#[derive(Debug, Clone, Queryable, Insertable, Selectable)]
+#[diesel(table_name = play_events)]
+pub struct PlayEvent {
+    pub play_event_id: Uuid,
+    pub ad_order_id: Uuid,
+    pub screen_id: Uuid,
+    pub played_at: PgTimestamp,
+    pub duration_ms: i32,
+    pub received_at: PgTimestamp,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayEventData {
+    /// Generated by the device so that a batch can be resent without counting plays twice.
+    pub event_id: Uuid,
+    pub ad_order_id: Uuid,
+    pub screen_id: Uuid,
+    pub played_at: i64,
+    pub duration_ms: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RejectedPlayEvent {
+    pub event_id: Uuid,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayEventBatchResult {
+    pub accepted: usize,
+    /// Events that had already been received.
+    pub duplicates: usize,
+    pub rejected: Vec<RejectedPlayEvent>,
+}
+
+/// Delivered plays of an order compared with the time that was booked.
+#[derive(Serialize, Deserialize)]
+pub struct DeliveryReport {
+    pub order_id: Uuid,
+    pub screen_id: Uuid,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub status: String,
+    pub booked_ms: i64,
+    pub play_count: i64,
+    /// Time the plays covered, capped at `booked_ms`.
+    pub played_ms: i64,
+    /// `played_ms / booked_ms`, from 0.0 to 1.0.
+    pub delivery_ratio: f64,
+    pub first_played_at: Option<i64>,
+    pub last_played_at: Option<i64>,
+}
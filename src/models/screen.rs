@@ -1,6 +1,10 @@
+use crate::models::address::Address;
 use crate::models::business::Business;
+use crate::models::screen_characteristics::ScreenCharacteristics;
 use diesel::{Associations, Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
 use crate::schema::screens;
@@ -12,17 +16,18 @@ pub struct Screen {
     pub screen_id: Uuid,
     pub screen_name: String,
     pub price_per_time: f64,
-    pub characteristics: String,
+    pub characteristics: ScreenCharacteristics,
     pub traffic: i32,
     pub business_id: Uuid,
     pub address_id: Uuid,
+    pub status: String,
 }
 
 #[derive(Serialize, Deserialize, Queryable)]
 pub struct ScreenData {
     pub screen_name: String,
     pub price_per_time: f64,
-    pub characteristics: String,
+    pub characteristics: ScreenCharacteristics,
     pub traffic: i32,
     pub business_id: Uuid,
     pub address_id: Uuid,
@@ -33,10 +38,98 @@ pub struct ScreenDataWithAddress {
     pub screen_id: Uuid,
     pub screen_name: String,
     pub price_per_time: f64,
-    pub characteristics: String,
+    pub characteristics: ScreenCharacteristics,
     pub traffic: i32,
     pub address_name: String,
     pub business_id: Uuid,
+    pub online_status: String,
+    pub last_seen_at: Option<i64>,
+}
+
+/// Screen submitted by a business for one of its own addresses.
+#[derive(Serialize, Deserialize)]
+pub struct BusinessScreenData {
+    pub screen_name: String,
+    pub price_per_time: f64,
+    pub characteristics: ScreenCharacteristics,
+    pub traffic: i32,
+    pub address_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScreenUpdateData {
+    pub screen_id: Uuid,
+    pub screen_name: String,
+    pub price_per_time: f64,
+    pub characteristics: ScreenCharacteristics,
+    pub traffic: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScreenStatusUpdate {
+    pub screen_id: Uuid,
+    pub new_status: ScreenStatus,
+}
+
+/// Outcome of deleting a screen: screens with order history are archived instead of removed.
+#[derive(Serialize, Deserialize)]
+pub struct ScreenDeletion {
+    pub screen_id: Uuid,
+    pub archived: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScreenStatus {
+    Pending,
+    Active,
+    Rejected,
+    Archived,
+}
+
+impl ScreenStatus {
+    pub fn can_transition_to(&self, next: ScreenStatus) -> bool {
+        use ScreenStatus::*;
+
+        matches!(
+            (self, next),
+            (Pending, Active)
+                | (Pending, Rejected)
+                | (Active, Rejected)
+                | (Active, Archived)
+                | (Rejected, Active)
+                | (Archived, Active)
+        )
+    }
+
+    /// Statuses that take the screen out of booking, so they need its upcoming orders settled.
+    pub fn is_withdrawn(&self) -> bool {
+        matches!(self, ScreenStatus::Rejected | ScreenStatus::Archived)
+    }
+}
+
+impl fmt::Display for ScreenStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenStatus::Pending => write!(f, "Pending"),
+            ScreenStatus::Active => write!(f, "Active"),
+            ScreenStatus::Rejected => write!(f, "Rejected"),
+            ScreenStatus::Archived => write!(f, "Archived"),
+        }
+    }
+}
+
+impl FromStr for ScreenStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "Pending" => Ok(ScreenStatus::Pending),
+            "Active" => Ok(ScreenStatus::Active),
+            "Rejected" => Ok(ScreenStatus::Rejected),
+            "Archived" => Ok(ScreenStatus::Archived),
+            _ => Err(format!("Unknown screen status: {}", status)),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -48,4 +141,66 @@ pub struct ScreenId {
 pub struct OptimalScreensData {
     pub user_budget: f64,
     pub ad_category_ids: Vec<Uuid>,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OptimalScreen {
+    pub screen: Screen,
+    /// Share of the requested categories that the screen's business covers, from 0 to 1.
+    pub relevance: f64,
+    pub expected_reach: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OptimalScreenSelection {
+    pub screens: Vec<OptimalScreen>,
+    pub total_price: f64,
+    /// Sum of the chosen screens' traffic over every billed unit of the campaign.
+    pub expected_reach: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AvailabilityQuery {
+    pub from: i64,
+    pub to: i64,
+    pub slot_minutes: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AvailabilityInterval {
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AvailabilitySlot {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub is_free: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScreenAvailability {
+    pub screen_id: Uuid,
+    pub from: i64,
+    pub to: i64,
+    pub booked: Vec<AvailabilityInterval>,
+    pub free: Vec<AvailabilityInterval>,
+    pub slots: Option<Vec<AvailabilitySlot>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NearbyScreensQuery {
+    pub lat: f64,
+    pub lng: f64,
+    pub radius_km: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct NearbyScreen {
+    pub screen: Screen,
+    pub address: Address,
+    pub distance_km: f64,
 }
@@ -0,0 +1,196 @@
+use crate::errors::{AppError, AppErrorType};
+use chrono::NaiveTime;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Jsonb;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::Write;
+
+/// Operating hours are written as `HH:MM`.
+const TIME_FORMAT: &str = "%H:%M";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resolution {
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Placement {
+    Indoor,
+    Outdoor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MediaType {
+    Image,
+    Video,
+    Html,
+}
+
+/// Daily window in which the screen plays ads. A `close` before `open` runs past midnight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatingHours {
+    pub open: String,
+    pub close: String,
+}
+
+impl OperatingHours {
+    fn parse(&self) -> Option<(NaiveTime, NaiveTime)> {
+        let open = NaiveTime::parse_from_str(&self.open, TIME_FORMAT).ok()?;
+        let close = NaiveTime::parse_from_str(&self.close, TIME_FORMAT).ok()?;
+        Some((open, close))
+    }
+
+    pub fn is_open_at(&self, time: NaiveTime) -> bool {
+        match self.parse() {
+            Some((open, close)) if open <= close => open <= time && time < close,
+            Some((open, close)) => time >= open || time < close,
+            None => false,
+        }
+    }
+}
+
+/// Stored in the `screens.characteristics` JSONB column. Fields are optional so screens
+/// created before characteristics were structured still load; `validate` enforces them for
+/// new and updated screens.
+#[derive(Debug, Clone, Serialize, Deserialize, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Jsonb)]
+pub struct ScreenCharacteristics {
+    pub resolution: Option<Resolution>,
+    pub orientation: Option<Orientation>,
+    pub size_inches: Option<f64>,
+    pub placement: Option<Placement>,
+    #[serde(default)]
+    pub media_types: Vec<MediaType>,
+    /// Screens without operating hours play around the clock.
+    pub operating_hours: Option<OperatingHours>,
+    pub description: Option<String>,
+}
+
+impl ScreenCharacteristics {
+    pub fn validate(&self) -> Result<(), AppError> {
+        let error = if self
+            .resolution
+            .as_ref()
+            .is_none_or(|resolution| resolution.width <= 0 || resolution.height <= 0)
+        {
+            Some("Screen resolution with a positive width and height is required")
+        } else if self.orientation.is_none() {
+            Some("Screen orientation is required")
+        } else if self
+            .size_inches
+            .is_none_or(|size_inches| size_inches <= 0.0)
+        {
+            Some("Screen size in inches is required")
+        } else if self.placement.is_none() {
+            Some("Screen placement is required")
+        } else if self.media_types.is_empty() {
+            Some("Screen must support at least one media type")
+        } else if self
+            .operating_hours
+            .as_ref()
+            .is_some_and(|operating_hours| operating_hours.parse().is_none())
+        {
+            Some("Operating hours must be written as HH:MM")
+        } else {
+            None
+        };
+
+        match error {
+            Some(message) => Err(AppError::new(
+                Some(message.to_string()),
+                None,
+                AppErrorType::ValidationError,
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromSql<Jsonb, Pg> for ScreenCharacteristics {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        let value = <serde_json::Value as FromSql<Jsonb, Pg>>::from_sql(bytes)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl ToSql<Jsonb, Pg> for ScreenCharacteristics {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        // JSONB values are sent as a version byte followed by the JSON text.
+        out.write_all(&[1])?;
+        serde_json::to_writer(out, self)?;
+        Ok(IsNull::No)
+    }
+}
+
+/// Query parameters for narrowing down `GET /screens/get_all`.
+#[derive(Serialize, Deserialize)]
+pub struct ScreenFilter {
+    pub orientation: Option<Orientation>,
+    pub placement: Option<Placement>,
+    pub media_type: Option<MediaType>,
+    pub min_size_inches: Option<f64>,
+    pub max_size_inches: Option<f64>,
+    pub min_width: Option<i32>,
+    pub min_height: Option<i32>,
+    /// `HH:MM` time at which the screen has to be playing.
+    pub open_at: Option<String>,
+}
+
+impl ScreenFilter {
+    pub fn open_at(&self) -> Result<Option<NaiveTime>, AppError> {
+        match &self.open_at {
+            Some(open_at) => NaiveTime::parse_from_str(open_at, TIME_FORMAT)
+                .map(Some)
+                .map_err(|_| {
+                    AppError::new(
+                        Some("open_at must be written as HH:MM".to_string()),
+                        None,
+                        AppErrorType::ValidationError,
+                    )
+                }),
+            None => Ok(None),
+        }
+    }
+
+    /// JSONB document that the characteristics of every matching screen contain, or `None` when
+    /// the filter asks for none of the listed values.
+    pub fn required_characteristics(&self) -> Option<serde_json::Value> {
+        let mut required = serde_json::Map::new();
+        if let Some(orientation) = self.orientation {
+            required.insert("orientation".to_string(), json!(orientation));
+        }
+        if let Some(placement) = self.placement {
+            required.insert("placement".to_string(), json!(placement));
+        }
+        if let Some(media_type) = self.media_type {
+            required.insert("media_types".to_string(), json!([media_type]));
+        }
+
+        (!required.is_empty()).then_some(serde_json::Value::Object(required))
+    }
+
+    /// Screens without operating hours match any `open_at`.
+    pub fn is_open(
+        &self,
+        characteristics: &ScreenCharacteristics,
+        open_at: Option<NaiveTime>,
+    ) -> bool {
+        open_at.is_none_or(|time| {
+            characteristics
+                .operating_hours
+                .as_ref()
+                .is_none_or(|operating_hours| operating_hours.is_open_at(time))
+        })
+    }
+}
@@ -1,3 +1,4 @@
+use crate::models::media::Renditions;
 use diesel::{Insertable, Queryable, Selectable};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -15,6 +16,14 @@ pub struct User {
     pub phone_number: String,
 }
 
+/// User as returned to clients, with the renditions of their image.
+#[derive(Serialize, Deserialize)]
+pub struct UserInfo {
+    #[serde(flatten)]
+    pub user: User,
+    pub img_renditions: Renditions,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct UserData {
     pub user_name: String,
@@ -0,0 +1,160 @@
+use crate::errors::{AppError, AppErrorType};
+use crate::payment_provider::PaymentProvider;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Amounts are tracked to the cent.
+const AMOUNT_TOLERANCE: f64 = 0.005;
+
+/// Prefix of the references the mock hands out, followed by the payment id.
+const REFERENCE_PREFIX: &str = "mock_";
+
+#[derive(Default)]
+struct MockTransaction {
+    authorized: f64,
+    captured: f64,
+    voided: f64,
+    refunded: f64,
+    /// Issued before a restart, so the amounts above were lost and are not checked.
+    restored: bool,
+}
+
+/// In-process gateway for local development. It keeps transactions in memory and signs
+/// webhooks with HMAC-SHA256. References issued before a restart are still accepted, without
+/// the amount checks.
+pub struct MockPaymentProvider {
+    webhook_secret: String,
+    transactions: Mutex<HashMap<String, MockTransaction>>,
+}
+
+impl MockPaymentProvider {
+    pub fn new(webhook_secret: String) -> Self {
+        MockPaymentProvider {
+            webhook_secret,
+            transactions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn with_transaction<F>(&self, reference: &str, f: F) -> Result<(), AppError>
+    where
+        F: FnOnce(&mut MockTransaction) -> Result<(), AppError>,
+    {
+        let mut transactions = self.transactions.lock().map_err(|err| {
+            AppError::new(
+                None,
+                Some(err.to_string()),
+                AppErrorType::SomethingWentWrong,
+            )
+        })?;
+
+        if !transactions.contains_key(reference) && !is_issued_reference(reference) {
+            return Err(payment_error("Unknown payment reference"));
+        }
+
+        let transaction = transactions
+            .entry(reference.to_string())
+            .or_insert_with(|| MockTransaction {
+                restored: true,
+                ..MockTransaction::default()
+            });
+        f(transaction)
+    }
+}
+
+fn is_issued_reference(reference: &str) -> bool {
+    reference
+        .strip_prefix(REFERENCE_PREFIX)
+        .is_some_and(|payment_id| Uuid::parse_str(payment_id).is_ok())
+}
+
+impl PaymentProvider for MockPaymentProvider {
+    fn authorize(&self, payment_id: Uuid, amount: f64) -> Result<String, AppError> {
+        if amount <= 0.0 {
+            return Err(payment_error("Amount must be positive"));
+        }
+
+        let reference = format!("{}{}", REFERENCE_PREFIX, payment_id.simple());
+        let mut transactions = self.transactions.lock().map_err(|err| {
+            AppError::new(
+                None,
+                Some(err.to_string()),
+                AppErrorType::SomethingWentWrong,
+            )
+        })?;
+        transactions.insert(
+            reference.clone(),
+            MockTransaction {
+                authorized: amount,
+                ..MockTransaction::default()
+            },
+        );
+
+        Ok(reference)
+    }
+
+    fn capture(&self, reference: &str, amount: f64) -> Result<(), AppError> {
+        self.with_transaction(reference, |transaction| {
+            let available = transaction.authorized - transaction.captured - transaction.voided;
+            if !transaction.restored && amount > available + AMOUNT_TOLERANCE {
+                return Err(payment_error("Capture exceeds the authorized amount"));
+            }
+            transaction.captured += amount;
+            Ok(())
+        })
+    }
+
+    fn void(&self, reference: &str, amount: f64) -> Result<(), AppError> {
+        self.with_transaction(reference, |transaction| {
+            let available = transaction.authorized - transaction.captured - transaction.voided;
+            if !transaction.restored && amount > available + AMOUNT_TOLERANCE {
+                return Err(payment_error("Void exceeds the uncaptured amount"));
+            }
+            transaction.voided += amount;
+            Ok(())
+        })
+    }
+
+    fn refund(&self, reference: &str, amount: f64) -> Result<(), AppError> {
+        self.with_transaction(reference, |transaction| {
+            if !transaction.restored
+                && amount > transaction.captured - transaction.refunded + AMOUNT_TOLERANCE
+            {
+                return Err(payment_error("Refund exceeds the captured amount"));
+            }
+            transaction.refunded += amount;
+            Ok(())
+        })
+    }
+
+    fn verify_webhook(&self, payload: &[u8], signature: &str) -> bool {
+        let signature = match decode_hex(signature) {
+            Some(signature) => signature,
+            None => return false,
+        };
+
+        let mut mac = match Hmac::<Sha256>::new_from_slice(self.webhook_secret.as_bytes()) {
+            Ok(mac) => mac,
+            Err(_) => return false,
+        };
+        mac.update(payload);
+        mac.verify_slice(&signature).is_ok()
+    }
+}
+
+fn payment_error(message: &str) -> AppError {
+    AppError::new(Some(message.to_string()), None, AppErrorType::PaymentError)
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
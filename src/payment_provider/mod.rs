@@ -0,0 +1,24 @@
+use crate::errors::AppError;
+use uuid::Uuid;
+
+pub mod mock;
+
+/// Gateway that moves the money behind `payments` rows.
+///
+/// Calls are made from `DbActor` handlers, so implementations are free to block.
+pub trait PaymentProvider: Send + Sync {
+    /// Reserves `amount` for the payment and returns the provider reference for it.
+    fn authorize(&self, payment_id: Uuid, amount: f64) -> Result<String, AppError>;
+
+    /// Takes `amount` out of a previous authorization.
+    fn capture(&self, reference: &str, amount: f64) -> Result<(), AppError>;
+
+    /// Releases `amount` of an authorization that has not been captured.
+    fn void(&self, reference: &str, amount: f64) -> Result<(), AppError>;
+
+    /// Returns `amount` of captured money to the customer.
+    fn refund(&self, reference: &str, amount: f64) -> Result<(), AppError>;
+
+    /// Checks that a webhook payload was signed by the provider.
+    fn verify_webhook(&self, payload: &[u8], signature: &str) -> bool;
+}
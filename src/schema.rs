@@ -13,9 +13,11 @@ diesel::table! {
         start_time -> Timestamptz,
         end_time -> Timestamptz,
         price -> Float8,
-        is_rejected -> Bool,
         ad_id -> Uuid,
         screen_id -> Uuid,
+        status -> Text,
+        campaign_id -> Nullable<Uuid>,
+        offline_flagged_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -24,6 +26,10 @@ diesel::table! {
         address_id -> Uuid,
         address_name -> Text,
         business_id -> Uuid,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+        city -> Nullable<Text>,
+        region -> Nullable<Text>,
     }
 }
 
@@ -45,6 +51,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    audit_logs (audit_log_id) {
+        audit_log_id -> Uuid,
+        admin_id -> Uuid,
+        action -> Text,
+        target_id -> Uuid,
+        owner_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     business_categories (category_id, business_id) {
         category_id -> Uuid,
@@ -63,6 +80,19 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    campaigns (campaign_id) {
+        campaign_id -> Uuid,
+        campaign_name -> Text,
+        user_id -> Uuid,
+        ad_id -> Uuid,
+        budget -> Float8,
+        total_price -> Float8,
+        provider_reference -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     categories (category_id) {
         category_id -> Uuid,
@@ -79,12 +109,47 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    media_renditions (media_id, rendition) {
+        media_id -> Text,
+        rendition -> Text,
+        rendition_media_id -> Text,
+        width -> Int4,
+        height -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     payments (payment_id) {
         payment_id -> Uuid,
         price -> Float8,
         user_id -> Uuid,
         ad_order_id -> Uuid,
+        status -> Text,
+        provider_reference -> Text,
+    }
+}
+
+diesel::table! {
+    play_events (play_event_id) {
+        play_event_id -> Uuid,
+        ad_order_id -> Uuid,
+        screen_id -> Uuid,
+        played_at -> Timestamptz,
+        duration_ms -> Int4,
+        received_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    screen_devices (screen_id) {
+        screen_id -> Uuid,
+        api_key_hash -> Text,
+        created_at -> Timestamptz,
+        last_seen_at -> Nullable<Timestamptz>,
+        software_version -> Nullable<Text>,
+        free_storage_bytes -> Nullable<Int8>,
     }
 }
 
@@ -93,10 +158,11 @@ diesel::table! {
         screen_id -> Uuid,
         screen_name -> Text,
         price_per_time -> Float8,
-        characteristics -> Text,
+        characteristics -> Jsonb,
         traffic -> Int4,
         business_id -> Uuid,
         address_id -> Uuid,
+        status -> Text,
     }
 }
 
@@ -114,15 +180,22 @@ diesel::table! {
 diesel::joinable!(ad_categories -> ads (ad_id));
 diesel::joinable!(ad_categories -> categories (category_id));
 diesel::joinable!(ad_orders -> ads (ad_id));
+diesel::joinable!(ad_orders -> campaigns (campaign_id));
 diesel::joinable!(ad_orders -> screens (screen_id));
 diesel::joinable!(addresses -> businesses (business_id));
 diesel::joinable!(ads -> users (user_id));
+diesel::joinable!(audit_logs -> admin (admin_id));
 diesel::joinable!(business_categories -> businesses (business_id));
 diesel::joinable!(business_categories -> categories (category_id));
+diesel::joinable!(campaigns -> ads (ad_id));
+diesel::joinable!(campaigns -> users (user_id));
 diesel::joinable!(incomes -> ad_orders (ad_order_id));
 diesel::joinable!(incomes -> businesses (business_id));
 diesel::joinable!(payments -> ad_orders (ad_order_id));
 diesel::joinable!(payments -> users (user_id));
+diesel::joinable!(play_events -> ad_orders (ad_order_id));
+diesel::joinable!(play_events -> screens (screen_id));
+diesel::joinable!(screen_devices -> screens (screen_id));
 diesel::joinable!(screens -> addresses (address_id));
 diesel::joinable!(screens -> businesses (business_id));
 
@@ -132,11 +205,16 @@ diesel::allow_tables_to_appear_in_same_query!(
     addresses,
     admin,
     ads,
+    audit_logs,
     business_categories,
     businesses,
+    campaigns,
     categories,
     incomes,
+    media_renditions,
     payments,
+    play_events,
+    screen_devices,
     screens,
     users,
 );